@@ -0,0 +1,149 @@
+use crate::animal::Generations;
+use crate::prestige::Prestige;
+use crate::statistics::Statistics;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::HashSet;
+use serde::Deserialize;
+use std::ops::Index;
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UnlockedAchievements>()
+            .add_event::<AchievementUnlocked>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(check_bred_conditions),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing).with_system(unlock_achievements),
+            );
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum Condition {
+    EggsHatched(u64),
+    Merges(u64),
+    /// Hatch or breed an animal of the named generation at least once; it does not have to be
+    /// kept, and rebirths do not reset it
+    EverBred(String),
+    LifetimeEarnings(f64),
+    UpgradesBought(u64),
+}
+
+impl Condition {
+    /// Current and target value; the condition is met once the current value reaches the target
+    pub fn progress(
+        &self,
+        statistics: &Statistics,
+        prestige: &Prestige,
+        generations: &Generations,
+    ) -> (f64, f64) {
+        match self {
+            Condition::EggsHatched(target) => (statistics.eggs_hatched as f64, *target as f64),
+            Condition::Merges(target) => (statistics.merges as f64, *target as f64),
+            Condition::EverBred(name) => {
+                let bred = generations
+                    .by_name(name)
+                    .map_or(false, |generation| statistics.bred.contains(&generation));
+                (if bred { 1. } else { 0. }, 1.)
+            }
+            Condition::LifetimeEarnings(target) => (prestige.lifetime_earnings.to_f64(), *target),
+            Condition::UpgradesBought(target) => {
+                (statistics.upgrades_bought as f64, *target as f64)
+            }
+        }
+    }
+}
+
+/// One entry of `farm.achievements.ron`
+#[derive(Deserialize, Clone, Debug)]
+pub struct Achievement {
+    /// Stable identifier used in save games
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub condition: Condition,
+}
+
+/// Index into the [`Achievements`] loaded from `farm.achievements.ron`
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct AchievementId(pub usize);
+
+#[derive(Clone, TypeUuid, Deserialize)]
+#[uuid = "b7d1c2a4-5e3f-4a86-8f0d-9c6e2d1b3a57"]
+pub struct Achievements {
+    achievements: Vec<Achievement>,
+}
+
+impl Achievements {
+    pub fn iter(&self) -> impl Iterator<Item = (AchievementId, &Achievement)> {
+        self.achievements
+            .iter()
+            .enumerate()
+            .map(|(index, achievement)| (AchievementId(index), achievement))
+    }
+
+    pub fn by_id(&self, id: &str) -> Option<AchievementId> {
+        self.achievements
+            .iter()
+            .position(|achievement| achievement.id == id)
+            .map(AchievementId)
+    }
+}
+
+impl Index<AchievementId> for Achievements {
+    type Output = Achievement;
+
+    fn index(&self, achievement: AchievementId) -> &Self::Output {
+        &self.achievements[achievement.0]
+    }
+}
+
+#[derive(Default)]
+pub struct UnlockedAchievements(pub HashSet<AchievementId>);
+
+pub struct AchievementUnlocked(pub AchievementId);
+
+/// Generations are loaded independently, so typos in their names can only be found here
+fn check_bred_conditions(achievements: Res<Achievements>, generations: Res<Generations>) {
+    for (_, achievement) in achievements.iter() {
+        if let Condition::EverBred(name) = &achievement.condition {
+            if generations.by_name(name).is_none() {
+                warn!(
+                    "Achievement '{}' can never be unlocked; there is no animal generation '{}'",
+                    achievement.id, name
+                );
+            }
+        }
+    }
+}
+
+fn unlock_achievements(
+    achievements: Res<Achievements>,
+    mut unlocked: ResMut<UnlockedAchievements>,
+    statistics: Res<Statistics>,
+    prestige: Res<Prestige>,
+    generations: Res<Generations>,
+    mut unlock_events: EventWriter<AchievementUnlocked>,
+) {
+    if !statistics.is_changed() && !prestige.is_changed() {
+        return;
+    }
+    for (id, achievement) in achievements.iter() {
+        if unlocked.0.contains(&id) {
+            continue;
+        }
+        let (current, target) =
+            achievement
+                .condition
+                .progress(&statistics, &prestige, &generations);
+        if current >= target {
+            unlocked.0.insert(id);
+            unlock_events.send(AchievementUnlocked(id));
+        }
+    }
+}
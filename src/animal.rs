@@ -1,29 +1,35 @@
 use crate::actions::Actions;
-use crate::audio::AnimalEvent;
-use crate::farm::{get_animal_in_reach, CurrentEggs, Egg};
-use crate::loading::{AudioAssets, TextureAssets};
-use crate::{GameState, ShmooLabels, ANIMAL_SIZE, UI_WIDTH, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::automation::WalkingTogether;
+use crate::clock::{GameClock, FIXED_TIMESTEP};
+use crate::events::{AnimalsMerged, EggHatched, MergeRejected};
+use crate::farm::{CurrentEggs, Egg};
+use crate::money::Money;
+use crate::playfield::Playfield;
+use crate::random::GameRng;
+use crate::spatial::SpatialIndex;
+use crate::{GameState, ShmooLabels, ANIMAL_SIZE};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::HashMap;
 use bevy_kira_audio::AudioSource;
-use rand::random;
-use strum::EnumIter;
+use rand::Rng;
+use serde::Deserialize;
+use std::ops::Index;
 
 pub struct AnimalPlugin;
 
 impl Plugin for AnimalPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_update(GameState::Playing)
-                .with_system(move_animals)
-                .with_system(update_animal_state),
-        )
-        .add_system_set(
-            SystemSet::on_update(GameState::Playing)
-                .after(ShmooLabels::ProcessActions)
-                .with_system(pick_up_animal)
-                .with_system(move_picked_animal)
-                .with_system(drop_animal),
-        );
+        app.add_system_set(SystemSet::on_update(GameState::Playing).with_system(wander))
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .after(ShmooLabels::ProcessActions)
+                    .after(ShmooLabels::IndexPlacements)
+                    .with_system(pick_up_animal)
+                    .with_system(move_picked_animal)
+                    .with_system(drop_animal),
+            );
     }
 }
 
@@ -31,72 +37,153 @@ impl Plugin for AnimalPlugin {
 pub struct Animal {
     pub generation: AnimalGeneration,
     pub state: AnimalState,
+    /// Seconds of income that were not paid out yet
+    pub(crate) unpaid_seconds: f64,
 }
 
-#[derive(PartialEq, EnumIter, Debug, Clone)]
-pub enum AnimalGeneration {
-    Chicken,
-    ChickenDuck,
-    ChickenDuckGoat,
-    ChickenDuckGoatSheep,
-    ChickenDuckGoatSheepPig,
-    ChickenDuckGoatSheepPigCow,
-    ChickenDuckGoatSheepPigCowRabbit,
+/// Index into the [`Generations`] loaded from `animals.generations.ron`
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub struct AnimalGeneration(pub usize);
+
+#[derive(Clone)]
+pub struct Generation {
+    pub name: String,
+    /// Still image for the user interface
+    pub texture: Handle<Image>,
+    pub sheet: SpriteSheet,
+    /// Built from the sheet once its image is loaded
+    pub atlas: Handle<TextureAtlas>,
+    pub animations: AnimationClips,
+    pub sound: Handle<AudioSource>,
+    pub color: Color,
+    pub money_per_second: Money,
+    /// Walking speed in units per second
+    pub speed: f32,
 }
 
-impl AnimalGeneration {
-    fn next(&self) -> Option<Self> {
-        match self {
-            AnimalGeneration::Chicken => Some(AnimalGeneration::ChickenDuck),
-            AnimalGeneration::ChickenDuck => Some(AnimalGeneration::ChickenDuckGoat),
-            AnimalGeneration::ChickenDuckGoat => Some(AnimalGeneration::ChickenDuckGoatSheep),
-            AnimalGeneration::ChickenDuckGoatSheep => {
-                Some(AnimalGeneration::ChickenDuckGoatSheepPig)
-            }
-            AnimalGeneration::ChickenDuckGoatSheepPig => {
-                Some(AnimalGeneration::ChickenDuckGoatSheepPigCow)
-            }
-            AnimalGeneration::ChickenDuckGoatSheepPigCow => {
-                Some(AnimalGeneration::ChickenDuckGoatSheepPigCowRabbit)
-            }
-            AnimalGeneration::ChickenDuckGoatSheepPigCowRabbit => None,
+impl Generation {
+    pub fn sprite(&self, transform: Transform) -> SpriteSheetBundle {
+        SpriteSheetBundle {
+            sprite: TextureAtlasSprite {
+                color: self.color,
+                index: self.animations.hatch[0],
+                ..default()
+            },
+            texture_atlas: self.atlas.clone(),
+            transform,
+            ..default()
         }
     }
+}
 
-    pub fn get_texture(&self, textures: &TextureAssets) -> Handle<Image> {
-        match self {
-            AnimalGeneration::Chicken => textures.chicken.clone(),
-            AnimalGeneration::ChickenDuck => textures.chicken_2.clone(),
-            AnimalGeneration::ChickenDuckGoat => textures.chicken_3.clone(),
-            AnimalGeneration::ChickenDuckGoatSheep => textures.chicken_4.clone(),
-            AnimalGeneration::ChickenDuckGoatSheepPig => textures.chicken_5.clone(),
-            AnimalGeneration::ChickenDuckGoatSheepPigCow => textures.chicken_6.clone(),
-            AnimalGeneration::ChickenDuckGoatSheepPigCowRabbit => textures.chicken_7.clone(),
+/// Frames of an animal on the farm, laid out in a grid of equally sized tiles
+#[derive(Clone)]
+pub struct SpriteSheet {
+    pub texture: Handle<Image>,
+    pub tile_size: Vec2,
+    pub columns: usize,
+    pub rows: usize,
+}
+
+/// Frames of the sprite sheet for every animation
+///
+/// The frames face right; animals walking left are flipped.
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct AnimationClips {
+    pub idle: Vec<usize>,
+    pub walk: Vec<usize>,
+    pub picked: Vec<usize>,
+    /// Played once when the animal appears on the farm
+    pub hatch: Vec<usize>,
+    /// Frames per second of all clips
+    pub fps: f32,
+}
+
+impl Default for AnimationClips {
+    fn default() -> Self {
+        AnimationClips {
+            idle: vec![0],
+            walk: vec![0],
+            picked: vec![0],
+            hatch: vec![0],
+            fps: 8.,
         }
     }
+}
 
-    pub fn get_audio(&self, audio: &AudioAssets) -> Handle<AudioSource> {
-        match self {
-            AnimalGeneration::Chicken => audio.chicken.clone(),
-            AnimalGeneration::ChickenDuck => audio.duck.clone(),
-            AnimalGeneration::ChickenDuckGoat => audio.goat.clone(),
-            AnimalGeneration::ChickenDuckGoatSheep => audio.sheep.clone(),
-            AnimalGeneration::ChickenDuckGoatSheepPig => audio.pig.clone(),
-            AnimalGeneration::ChickenDuckGoatSheepPigCow => audio.cow.clone(),
-            AnimalGeneration::ChickenDuckGoatSheepPigCowRabbit => audio.yipee.clone(),
+/// All animal generations and the recipes to breed them; the first generation hatches from eggs
+#[derive(Clone, TypeUuid)]
+#[uuid = "0d8b5a3c-7d0e-4d8f-9a4e-5b3f3c8e2a61"]
+pub struct Generations {
+    generations: Vec<Generation>,
+    recipes: HashMap<(AnimalGeneration, AnimalGeneration), AnimalGeneration>,
+}
+
+impl Generations {
+    pub fn new(generations: Vec<Generation>) -> Self {
+        Generations {
+            generations,
+            recipes: HashMap::default(),
         }
     }
 
-    pub fn money_per_second(&self) -> f32 {
-        match self {
-            AnimalGeneration::Chicken => 0.5,
-            AnimalGeneration::ChickenDuck => 1.5,
-            AnimalGeneration::ChickenDuckGoat => 4.,
-            AnimalGeneration::ChickenDuckGoatSheep => 9.5,
-            AnimalGeneration::ChickenDuckGoatSheepPig => 21.,
-            AnimalGeneration::ChickenDuckGoatSheepPigCow => 44.5,
-            AnimalGeneration::ChickenDuckGoatSheepPigCowRabbit => 92.,
-        }
+    /// Returns the previous child if there already was a recipe for the given parents
+    pub fn add_recipe(
+        &mut self,
+        first: AnimalGeneration,
+        second: AnimalGeneration,
+        child: AnimalGeneration,
+    ) -> Option<AnimalGeneration> {
+        self.recipes
+            .insert(Self::recipe_key(first, second), child)
+            .filter(|existing| *existing != child)
+    }
+
+    /// The generation two animals merge into, or `None` if there is no recipe for them
+    pub fn breed(
+        &self,
+        first: AnimalGeneration,
+        second: AnimalGeneration,
+    ) -> Option<AnimalGeneration> {
+        self.recipes.get(&Self::recipe_key(first, second)).copied()
+    }
+
+    fn recipe_key(
+        first: AnimalGeneration,
+        second: AnimalGeneration,
+    ) -> (AnimalGeneration, AnimalGeneration) {
+        (first.min(second), first.max(second))
+    }
+
+    pub fn first(&self) -> AnimalGeneration {
+        AnimalGeneration(0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (AnimalGeneration, &Generation)> {
+        self.generations
+            .iter()
+            .enumerate()
+            .map(|(index, generation)| (AnimalGeneration(index), generation))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Generation> {
+        self.generations.iter_mut()
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<AnimalGeneration> {
+        self.generations
+            .iter()
+            .position(|generation| generation.name == name)
+            .map(AnimalGeneration)
+    }
+}
+
+impl Index<AnimalGeneration> for Generations {
+    type Output = Generation;
+
+    fn index(&self, generation: AnimalGeneration) -> &Self::Output {
+        &self.generations[generation.0]
     }
 }
 
@@ -107,13 +194,12 @@ pub enum AnimalState {
 }
 
 impl AnimalState {
-    fn update(&mut self, seconds_since_startup: f64) {
+    fn update(&mut self, seconds_since_startup: f64, rng: &mut GameRng) {
         match self {
             AnimalState::Idle { .. } => {
                 *self = AnimalState::Moving {
                     since: seconds_since_startup,
-                    velocity: Vec2::new((random::<f32>() * 2.) - 1., (random::<f32>() * 2.) - 1.)
-                        .normalize(),
+                    velocity: random_direction(rng),
                 }
             }
             AnimalState::Moving { .. } => {
@@ -124,15 +210,14 @@ impl AnimalState {
         }
     }
 
-    fn change_direction(&mut self, seconds_since_startup: f64) {
+    fn change_direction(&mut self, seconds_since_startup: f64, rng: &mut GameRng) {
         match self {
             AnimalState::Moving {
                 ref mut since,
                 ref mut velocity,
             } => {
                 *since = seconds_since_startup - 1.0;
-                *velocity =
-                    Vec2::new((random::<f32>() * 2.) - 1., (random::<f32>() * 2.) - 1.).normalize();
+                *velocity = random_direction(rng);
             }
             _ => {}
         }
@@ -146,77 +231,80 @@ impl AnimalState {
     }
 }
 
+fn random_direction(rng: &mut GameRng) -> Vec2 {
+    Vec2::new(rng.gen_range(-1.0..1.), rng.gen_range(-1.0..1.)).normalize()
+}
+
 impl Animal {
-    pub(crate) fn new(seconds_since_startup: f64) -> Self {
+    pub(crate) fn new(generation: AnimalGeneration, seconds_since_startup: f64) -> Self {
         Animal {
-            generation: AnimalGeneration::Chicken,
+            generation,
             state: AnimalState::Idle {
                 since: seconds_since_startup,
             },
+            unpaid_seconds: 0.,
         }
     }
 }
 
-fn update_animal_state(mut animals: Query<&mut Animal, Without<Picked>>, time: Res<Time>) {
-    for mut animal in animals.iter_mut() {
-        if !animal
-            .state
-            .can_update_movement(time.seconds_since_startup())
-        {
-            continue;
-        }
-        let chance = match animal.state {
-            AnimalState::Idle { .. } => 0.02,
-            AnimalState::Moving { .. } => 0.003,
-        };
-        if random::<f32>() < chance {
-            animal.state.update(time.seconds_since_startup());
+/// Let the animals walk around in fixed timesteps
+///
+/// State changes and movement share one system, so they interleave the same way no matter
+/// how many steps are due in a single frame.
+fn wander(
+    mut animals: Query<(&mut Transform, &mut Animal), (Without<Picked>, Without<WalkingTogether>)>,
+    generations: Res<Generations>,
+    playfield: Res<Playfield>,
+    clock: Res<GameClock>,
+    mut rng: ResMut<GameRng>,
+) {
+    let step = FIXED_TIMESTEP.as_secs_f32();
+    for seconds_since_startup in clock.fixed_steps() {
+        for (mut transform, mut animal) in animals.iter_mut() {
+            update_animal_state(&mut animal, seconds_since_startup, &mut rng);
+            if let AnimalState::Moving { velocity, .. } = animal.state {
+                let speed = generations[animal.generation].speed;
+                transform.translation.x += velocity.x * speed * step;
+                transform.translation.y += velocity.y * speed * step;
+
+                let position = transform.translation.truncate();
+                if !playfield.contains(position) {
+                    animal
+                        .state
+                        .change_direction(seconds_since_startup, &mut rng);
+                    let position = playfield.clamp(position);
+                    transform.translation.x = position.x;
+                    transform.translation.y = position.y;
+                }
+            }
         }
     }
 }
 
-fn move_animals(
-    mut animals: Query<(&mut Transform, &mut Animal), Without<Picked>>,
-    time: Res<Time>,
-) {
-    for (mut transform, mut animal) in animals.iter_mut() {
-        if let AnimalState::Moving { velocity, .. } = animal.state {
-            transform.translation.x += velocity.x;
-            transform.translation.y += velocity.y;
-
-            if transform.translation.x < ANIMAL_SIZE / 2. - WINDOW_WIDTH / 2.
-                || transform.translation.x > WINDOW_WIDTH / 2. - ANIMAL_SIZE / 2. - UI_WIDTH
-                || transform.translation.y < ANIMAL_SIZE / 2. - WINDOW_HEIGHT / 2.
-                || transform.translation.y > WINDOW_HEIGHT / 2. - ANIMAL_SIZE / 2.
-            {
-                animal.state.change_direction(time.seconds_since_startup());
-                transform.translation.x = transform.translation.x.clamp(
-                    ANIMAL_SIZE / 2. - WINDOW_WIDTH / 2.,
-                    WINDOW_WIDTH / 2. - ANIMAL_SIZE / 2. - UI_WIDTH,
-                );
-                transform.translation.y = transform.translation.y.clamp(
-                    ANIMAL_SIZE / 2. - WINDOW_HEIGHT / 2.,
-                    WINDOW_HEIGHT / 2. - ANIMAL_SIZE / 2.,
-                );
-            }
-        }
+/// The chances are per fixed timestep
+fn update_animal_state(animal: &mut Animal, seconds_since_startup: f64, rng: &mut GameRng) {
+    if !animal.state.can_update_movement(seconds_since_startup) {
+        return;
+    }
+    let chance = match animal.state {
+        AnimalState::Idle { .. } => 0.02,
+        AnimalState::Moving { .. } => 0.003,
+    };
+    if rng.gen::<f32>() < chance {
+        animal.state.update(seconds_since_startup, rng);
     }
 }
 
-fn move_picked_animal(mut animal: Query<&mut Transform, With<Picked>>, actions: Res<Actions>) {
+fn move_picked_animal(
+    mut animal: Query<&mut Transform, With<Picked>>,
+    actions: Res<Actions>,
+    playfield: Res<Playfield>,
+) {
     if let Ok(mut transform) = animal.get_single_mut() {
         if let Some(position) = actions.position {
+            let position = playfield.clamp(position);
             transform.translation.x = position.x;
             transform.translation.y = position.y;
-
-            transform.translation.x = transform.translation.x.clamp(
-                ANIMAL_SIZE / 2. - WINDOW_WIDTH / 2.,
-                WINDOW_WIDTH / 2. - ANIMAL_SIZE / 2. - UI_WIDTH,
-            );
-            transform.translation.y = transform.translation.y.clamp(
-                ANIMAL_SIZE / 2. - WINDOW_HEIGHT / 2.,
-                WINDOW_HEIGHT / 2. - ANIMAL_SIZE / 2.,
-            );
         }
     }
 }
@@ -224,42 +312,94 @@ fn move_picked_animal(mut animal: Query<&mut Transform, With<Picked>>, actions:
 #[derive(Component)]
 pub struct Picked;
 
+/// Hatching and merging, shared by the player's input and the automation
+#[derive(SystemParam)]
+pub struct Breeder<'w, 's> {
+    commands: Commands<'w, 's>,
+    generations: Res<'w, Generations>,
+    clock: Res<'w, GameClock>,
+    hatched: EventWriter<'w, 's, EggHatched>,
+    merged: EventWriter<'w, 's, AnimalsMerged>,
+    current_eggs: ResMut<'w, CurrentEggs>,
+}
+
+impl<'w, 's> Breeder<'w, 's> {
+    pub fn hatch(&mut self, egg: Entity, transform: Transform) {
+        self.commands.entity(egg).despawn();
+
+        let animal = Animal::new(self.generations.first(), self.clock.seconds_since_startup());
+        let generation = animal.generation;
+        let entity = self
+            .commands
+            .spawn_bundle(self.generations[generation].sprite(transform))
+            .insert(animal)
+            .id();
+        self.hatched.send(EggHatched {
+            animal: entity,
+            generation,
+            position: transform.translation.truncate(),
+        });
+        self.current_eggs.0 -= 1;
+    }
+
+    /// Replace both animals by their child at the given transform
+    ///
+    /// Returns `false` and leaves the animals alone if there is no recipe for them.
+    pub fn merge(
+        &mut self,
+        (first, first_animal): (Entity, &Animal),
+        (second, second_animal): (Entity, &Animal),
+        transform: Transform,
+    ) -> bool {
+        let child = match self
+            .generations
+            .breed(first_animal.generation, second_animal.generation)
+        {
+            Some(child) => child,
+            None => return false,
+        };
+        self.commands.entity(first).despawn();
+        self.commands.entity(second).despawn();
+        let entity = self
+            .commands
+            .spawn_bundle(self.generations[child].sprite(transform))
+            .insert(Animal::new(child, self.clock.seconds_since_startup()))
+            .id();
+        self.merged.send(AnimalsMerged {
+            animal: entity,
+            from: (first_animal.generation, second_animal.generation),
+            to: child,
+            position: transform.translation.truncate(),
+        });
+
+        true
+    }
+}
+
 fn pick_up_animal(
     mut commands: Commands,
-    textures: Res<TextureAssets>,
-    time: Res<Time>,
-    mut hatch_events: EventWriter<AnimalEvent>,
-    mut current_eggs: ResMut<CurrentEggs>,
-    animals: Query<(Entity, &Transform, &Animal), Without<Picked>>,
-    eggs: Query<(Entity, &Transform), (Without<Animal>, With<Egg>)>,
+    mut breeder: Breeder,
+    index: Res<SpatialIndex>,
+    animals: Query<(), (With<Animal>, Without<Picked>)>,
+    eggs: Query<&Transform, (Without<Animal>, With<Egg>)>,
     actions: Res<Actions>,
 ) {
     if !actions.just_pressed {
         return;
     }
     if let Some(position) = actions.position {
-        for (egg, egg_position) in eggs.iter() {
-            if position.distance(Vec2::new(
-                egg_position.translation.x,
-                egg_position.translation.y,
-            )) < 32.
-            {
-                commands.entity(egg).despawn();
-
-                let animal = Animal::new(time.seconds_since_startup());
-                hatch_events.send(AnimalEvent(animal.generation.clone()));
-                commands
-                    .spawn_bundle(SpriteBundle {
-                        texture: animal.generation.get_texture(&textures),
-                        transform: egg_position.clone(),
-                        ..default()
-                    })
-                    .insert(animal);
-                current_eggs.0 -= 1;
-                return;
-            }
+        let reach = ANIMAL_SIZE / 2.;
+        if let Some(egg) = index
+            .eggs()
+            .nearest(position, reach, |egg| eggs.contains(egg))
+        {
+            breeder.hatch(egg, *eggs.get(egg).unwrap());
+            return;
         }
-        if let Some(entity) = get_animal_in_reach(&animals, &position, ANIMAL_SIZE / 2.) {
+        if let Some(entity) = index
+            .animals()
+            .nearest(position, reach, |animal| animals.contains(animal))
+        {
             commands.entity(entity).insert(Picked);
         }
     }
@@ -267,12 +407,12 @@ fn pick_up_animal(
 
 fn drop_animal(
     mut commands: Commands,
-    time: Res<Time>,
-    textures: Res<TextureAssets>,
-    mut animal_events: EventWriter<AnimalEvent>,
-    animals: Query<(Entity, &Transform, &Animal), Without<Picked>>,
+    mut breeder: Breeder,
+    index: Res<SpatialIndex>,
+    animals: Query<(&Transform, &Animal), Without<Picked>>,
     picked_animal: Query<(Entity, &Animal), With<Picked>>,
     actions: Res<Actions>,
+    mut rejected: EventWriter<MergeRejected>,
 ) {
     if !actions.just_released {
         return;
@@ -280,30 +420,22 @@ fn drop_animal(
     if let Ok((picked_animal_entity, picked_animal)) = picked_animal.get_single() {
         if let Some(position) = actions.position {
             if let Some(dropped_on_animal) =
-                get_animal_in_reach(&animals, &position, ANIMAL_SIZE / 2.)
+                index
+                    .animals()
+                    .nearest(position, ANIMAL_SIZE / 2., |animal| {
+                        animals.contains(animal)
+                    })
             {
-                if picked_animal.generation == animals.get(dropped_on_animal).unwrap().2.generation
-                {
-                    if let Some(next_generation) = picked_animal.generation.next() {
-                        animal_events.send(AnimalEvent(next_generation.clone()));
-                        commands.entity(dropped_on_animal).despawn();
-                        commands
-                            .spawn_bundle(SpriteBundle {
-                                texture: next_generation.get_texture(&textures),
-                                transform: animals.get(dropped_on_animal).unwrap().1.clone(),
-                                ..default()
-                            })
-                            .insert(Animal {
-                                generation: next_generation,
-                                state: AnimalState::Idle {
-                                    since: time.seconds_since_startup(),
-                                },
-                            });
-                        commands.entity(picked_animal_entity).despawn();
-                    } else {
-                        commands.entity(picked_animal_entity).remove::<Picked>();
-                    }
-                } else {
+                let (transform, dropped_on) = animals.get(dropped_on_animal).unwrap();
+                if !breeder.merge(
+                    (picked_animal_entity, picked_animal),
+                    (dropped_on_animal, dropped_on),
+                    *transform,
+                ) {
+                    rejected.send(MergeRejected {
+                        generations: (picked_animal.generation, dropped_on.generation),
+                        position: transform.translation.truncate(),
+                    });
                     commands.entity(picked_animal_entity).remove::<Picked>();
                 }
             } else {
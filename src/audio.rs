@@ -1,36 +1,336 @@
-use crate::animal::AnimalGeneration;
-use crate::loading::AudioAssets;
-use crate::GameState;
+use crate::animal::Generations;
+use crate::effects::ScreenShake;
+use crate::events::{AnimalsMerged, EggHatched, MergeRejected};
+use crate::playfield::Playfield;
+use crate::settings::Settings;
+use crate::{GameState, MainCamera};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioApp, AudioChannel, AudioPlugin};
+use bevy::utils::HashMap;
+use bevy_kira_audio::{
+    AudioApp, AudioChannel, AudioPlugin, AudioSource, InstanceHandle, PlaybackState,
+};
+use rand::Rng;
+use std::ops::RangeInclusive;
 
 pub struct InternalAudioPlugin;
 
 impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<AnimalEvent>()
-            .add_plugin(AudioPlugin)
-            .add_audio_channel::<Background>()
-            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(start_audio))
+        app.add_plugin(AudioPlugin)
+            .add_audio_channel::<Effect<0>>()
+            .add_audio_channel::<Effect<1>>()
+            .add_audio_channel::<Effect<2>>()
+            .add_audio_channel::<Effect<3>>()
+            .init_resource::<EffectVoices>()
             .add_system_set(SystemSet::on_update(GameState::Playing).with_system(animal_sounds));
     }
 }
 
-struct Background;
+/// One of the channels sound effects are played on
+///
+/// Pitch and volume can only be set per channel, so every effect gets a channel of its own
+/// for as long as it plays. This also caps the number of effects playing at once.
+struct Effect<const INDEX: usize>;
 
-pub struct AnimalEvent(pub AnimalGeneration);
+const EFFECT_CHANNELS: usize = 4;
+/// Panning of a sound at the edge of the view; 0.5 would be fully left or right
+const MAX_PANNING: f32 = 0.4;
+/// Volume lost by a sound in the corner of the view
+const MAX_ATTENUATION: f32 = 0.3;
 
-fn start_audio(audio_assets: Res<AudioAssets>, audio: Res<AudioChannel<Background>>) {
-    audio.set_volume(0.2);
-    audio.play_looped(audio_assets.background.clone());
+/// How often and how a clip may be played for one kind of event
+struct SoundPolicy {
+    /// Seconds until the same clip may be played again
+    cooldown: f64,
+    /// Instances of the same clip that may play at once
+    max_instances: usize,
+    pitch: RangeInclusive<f32>,
+    volume: RangeInclusive<f32>,
+}
+
+/// Hatching happens often, so it is higher and quieter than the generation's full sound
+const HATCH_SOUND: SoundPolicy = SoundPolicy {
+    cooldown: 0.15,
+    max_instances: 2,
+    pitch: 1.2..=1.4,
+    volume: 0.4..=0.6,
+};
+
+const MERGE_SOUND: SoundPolicy = SoundPolicy {
+    cooldown: 0.05,
+    max_instances: 3,
+    pitch: 0.95..=1.05,
+    volume: 0.9..=1.,
+};
+
+/// A low grumble of the animal that was dropped
+const REJECTED_MERGE_SOUND: SoundPolicy = SoundPolicy {
+    cooldown: 0.3,
+    max_instances: 1,
+    pitch: 0.6..=0.7,
+    volume: 0.5..=0.6,
+};
+
+/// Effects that are currently playing and when each clip was played last
+#[derive(Default)]
+struct EffectVoices {
+    playing: Vec<(Handle<AudioSource>, usize, InstanceHandle)>,
+    last_played: HashMap<Handle<AudioSource>, f64>,
+}
+
+impl EffectVoices {
+    /// A free channel for the clip, unless its policy forbids to play it right now
+    fn channel_for(
+        &self,
+        clip: &Handle<AudioSource>,
+        policy: &SoundPolicy,
+        now: f64,
+    ) -> Option<usize> {
+        if let Some(last_played) = self.last_played.get(clip) {
+            if now - last_played < policy.cooldown {
+                return None;
+            }
+        }
+        let instances = self
+            .playing
+            .iter()
+            .filter(|(playing, ..)| playing == clip)
+            .count();
+        if instances >= policy.max_instances {
+            return None;
+        }
+
+        (0..EFFECT_CHANNELS).find(|channel| self.playing.iter().all(|(_, busy, _)| busy != channel))
+    }
+}
+
+#[derive(SystemParam)]
+struct EffectPlayer<'w, 's> {
+    voices: ResMut<'w, EffectVoices>,
+    settings: Res<'w, Settings>,
+    time: Res<'w, Time>,
+    channel_0: Res<'w, AudioChannel<Effect<0>>>,
+    channel_1: Res<'w, AudioChannel<Effect<1>>>,
+    channel_2: Res<'w, AudioChannel<Effect<2>>>,
+    channel_3: Res<'w, AudioChannel<Effect<3>>>,
+    playfield: Res<'w, Playfield>,
+    camera: Query<'w, 's, &'static GlobalTransform, With<MainCamera>>,
+    shake: Res<'w, ScreenShake>,
+}
+
+impl<'w, 's> EffectPlayer<'w, 's> {
+    /// Play the clip unless the policy or the number of free channels forbids it
+    ///
+    /// The sound is panned and attenuated by its position relative to the camera.
+    fn play(&mut self, clip: &Handle<AudioSource>, policy: &SoundPolicy, position: Vec2) {
+        let now = self.time.seconds_since_startup();
+        let mut playing = std::mem::take(&mut self.voices.playing);
+        playing.retain(|(_, channel, instance)| {
+            self.state(*channel, instance.clone()) != PlaybackState::Stopped
+        });
+        self.voices.playing = playing;
+        if let Some(channel) = self.voices.channel_for(clip, policy, now) {
+            let mut rng = rand::thread_rng();
+            let (panning, attenuation) = self.spatialize(position);
+            let volume = self.settings.audio.effects_volume()
+                * attenuation
+                * rng.gen_range(policy.volume.clone());
+            let pitch = rng.gen_range(policy.pitch.clone());
+            let instance = self.start(channel, clip.clone(), volume, pitch, panning);
+            self.voices.playing.push((clip.clone(), channel, instance));
+            self.voices.last_played.insert(clip.clone(), now);
+        }
+    }
+
+    /// Panning and volume factor for a sound at the given world position
+    ///
+    /// Sounds are heard from the resting position of the camera, so shaking it does not pan them.
+    fn spatialize(&self, position: Vec2) -> (f32, f32) {
+        let camera = self
+            .camera
+            .get_single()
+            .map_or(Vec2::ZERO, |camera| camera.translation.truncate())
+            - self.shake.offset();
+
+        spatialize(position, camera, self.playfield.window_size())
+    }
+
+    fn start(
+        &self,
+        channel: usize,
+        clip: Handle<AudioSource>,
+        volume: f32,
+        pitch: f32,
+        panning: f32,
+    ) -> InstanceHandle {
+        match channel {
+            0 => start_on(&self.channel_0, clip, volume, pitch, panning),
+            1 => start_on(&self.channel_1, clip, volume, pitch, panning),
+            2 => start_on(&self.channel_2, clip, volume, pitch, panning),
+            _ => start_on(&self.channel_3, clip, volume, pitch, panning),
+        }
+    }
+
+    fn state(&self, channel: usize, instance: InstanceHandle) -> PlaybackState {
+        match channel {
+            0 => self.channel_0.state(instance),
+            1 => self.channel_1.state(instance),
+            2 => self.channel_2.state(instance),
+            _ => self.channel_3.state(instance),
+        }
+    }
+}
+
+/// Panning and volume factor for a sound at `position`, heard by a camera at `camera` that
+/// sees an area of `view_size`
+fn spatialize(position: Vec2, camera: Vec2, view_size: Vec2) -> (f32, f32) {
+    let offset = ((position - camera) / (view_size / 2.)).clamp(-Vec2::ONE, Vec2::ONE);
+    let panning = 0.5 + MAX_PANNING * offset.x;
+    let attenuation = 1. - MAX_ATTENUATION * (offset.length() / 2f32.sqrt());
+
+    (panning, attenuation)
+}
+
+fn start_on<T>(
+    channel: &AudioChannel<T>,
+    clip: Handle<AudioSource>,
+    volume: f32,
+    pitch: f32,
+    panning: f32,
+) -> InstanceHandle {
+    channel.set_volume(volume);
+    channel.set_playback_rate(pitch);
+    channel.set_panning(panning);
+    channel.play(clip)
 }
 
 fn animal_sounds(
-    mut events: EventReader<AnimalEvent>,
-    audio_assets: Res<AudioAssets>,
-    audio: Res<Audio>,
+    mut hatched: EventReader<EggHatched>,
+    mut merged: EventReader<AnimalsMerged>,
+    mut rejected: EventReader<MergeRejected>,
+    generations: Res<Generations>,
+    mut effects: EffectPlayer,
 ) {
-    for event in events.iter() {
-        audio.play(event.0.get_audio(&audio_assets));
+    for event in hatched.iter() {
+        effects.play(
+            &generations[event.generation].sound,
+            &HATCH_SOUND,
+            event.position,
+        );
+    }
+    for event in merged.iter() {
+        effects.play(&generations[event.to].sound, &MERGE_SOUND, event.position);
+    }
+    for event in rejected.iter() {
+        effects.play(
+            &generations[event.generations.0].sound,
+            &REJECTED_MERGE_SOUND,
+            event.position,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::HandleId;
+
+    const POLICY: SoundPolicy = SoundPolicy {
+        cooldown: 0.5,
+        max_instances: 2,
+        pitch: 1.0..=1.0,
+        volume: 1.0..=1.0,
+    };
+
+    fn clip() -> Handle<AudioSource> {
+        Handle::weak(HandleId::random::<AudioSource>())
+    }
+
+    /// Pretend the clip started playing on the channel at the given time
+    fn start(voices: &mut EffectVoices, clip: &Handle<AudioSource>, channel: usize, now: f64) {
+        let instance = AudioChannel::<Music>::default().play(clip.clone());
+        voices.playing.push((clip.clone(), channel, instance));
+        voices.last_played.insert(clip.clone(), now);
+    }
+
+    const VIEW: Vec2 = Vec2::new(800., 600.);
+
+    fn assert_close((panning, attenuation): (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (panning - expected.0).abs() < 1e-6 && (attenuation - expected.1).abs() < 1e-6,
+            "{:?} != {:?}",
+            (panning, attenuation),
+            expected
+        );
+    }
+
+    #[test]
+    fn sounds_in_front_of_the_camera_are_centered_and_loud() {
+        assert_close(spatialize(Vec2::ZERO, Vec2::ZERO, VIEW), (0.5, 1.));
+        assert_close(
+            spatialize(Vec2::new(300., -50.), Vec2::new(300., -50.), VIEW),
+            (0.5, 1.),
+        );
+    }
+
+    #[test]
+    fn sounds_at_the_edge_are_panned_to_their_side() {
+        let edge_attenuation = 1. - MAX_ATTENUATION / 2f32.sqrt();
+
+        assert_close(
+            spatialize(Vec2::new(400., 0.), Vec2::ZERO, VIEW),
+            (0.5 + MAX_PANNING, edge_attenuation),
+        );
+        assert_close(
+            spatialize(Vec2::new(-400., 0.), Vec2::ZERO, VIEW),
+            (0.5 - MAX_PANNING, edge_attenuation),
+        );
+        // only the horizontal offset pans
+        assert_close(
+            spatialize(Vec2::new(0., 300.), Vec2::ZERO, VIEW),
+            (0.5, edge_attenuation),
+        );
+    }
+
+    #[test]
+    fn sounds_outside_the_view_are_clamped_to_its_corner() {
+        assert_close(
+            spatialize(Vec2::new(-5000., 5000.), Vec2::ZERO, VIEW),
+            (0.5 - MAX_PANNING, 1. - MAX_ATTENUATION),
+        );
+    }
+
+    #[test]
+    fn waits_for_the_cooldown() {
+        let mut voices = EffectVoices::default();
+        let clip = clip();
+        start(&mut voices, &clip, 0, 1.);
+        voices.playing.clear();
+
+        assert_eq!(voices.channel_for(&clip, &POLICY, 1.2), None);
+        assert_eq!(voices.channel_for(&clip, &POLICY, 1.5), Some(0));
+    }
+
+    #[test]
+    fn limits_the_instances_of_one_clip() {
+        let mut voices = EffectVoices::default();
+        let busy = clip();
+        start(&mut voices, &busy, 0, 0.);
+        assert_eq!(voices.channel_for(&busy, &POLICY, 1.), Some(1));
+        start(&mut voices, &busy, 1, 1.);
+
+        assert_eq!(voices.channel_for(&busy, &POLICY, 2.), None);
+        assert_eq!(voices.channel_for(&clip(), &POLICY, 2.), Some(2));
+    }
+
+    #[test]
+    fn needs_a_free_channel() {
+        let mut voices = EffectVoices::default();
+        for channel in 0..EFFECT_CHANNELS {
+            start(&mut voices, &clip(), channel, 0.);
+        }
+
+        assert_eq!(voices.channel_for(&clip(), &POLICY, 1.), None);
     }
 }
@@ -0,0 +1,292 @@
+use crate::animal::{Animal, Breeder, Generations, Picked};
+use crate::clock::{GameClock, FIXED_TIMESTEP};
+use crate::farm::Egg;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::time::Duration;
+
+/// Seconds between two automation ticks before buying any speed upgrade
+pub const DEFAULT_AUTOMATION_INTERVAL: f32 = 5.;
+/// The speed upgrade cannot push the interval below this
+pub const MIN_AUTOMATION_INTERVAL: f32 = 0.5;
+/// Animals merge once they got this close to each other
+const MERGE_DISTANCE: f32 = 8.;
+/// Animals walking towards a partner are faster than wandering ones
+const WALK_TOGETHER_SPEED_FACTOR: f32 = 2.;
+
+pub struct AutomationPlugin;
+
+impl Plugin for AutomationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Automation>()
+            .init_resource::<AutomationTimer>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(update_automation_timer)
+                    .with_system(automate.after(update_automation_timer))
+                    .with_system(walk_together),
+            );
+    }
+}
+
+/// What the automation upgrades of the current run unlocked
+pub struct Automation {
+    pub auto_hatch: bool,
+    pub auto_merge: bool,
+    /// Seconds between two automation ticks
+    pub interval: f32,
+}
+
+impl Default for Automation {
+    fn default() -> Self {
+        Automation {
+            auto_hatch: false,
+            auto_merge: false,
+            interval: DEFAULT_AUTOMATION_INTERVAL,
+        }
+    }
+}
+
+pub struct AutomationTimer(pub Timer);
+
+impl Default for AutomationTimer {
+    fn default() -> Self {
+        AutomationTimer(Timer::from_seconds(DEFAULT_AUTOMATION_INTERVAL, true))
+    }
+}
+
+/// An animal on its way to merge with its partner
+///
+/// Both partners get this component pointing at each other.
+#[derive(Component)]
+pub struct WalkingTogether {
+    pub partner: Entity,
+}
+
+fn update_automation_timer(automation: Res<Automation>, mut timer: ResMut<AutomationTimer>) {
+    if automation.is_changed() {
+        timer
+            .0
+            .set_duration(Duration::from_secs_f32(automation.interval));
+    }
+}
+
+/// Hatch one egg and send one pair of animals of the same generation towards each other per tick
+fn automate(
+    mut commands: Commands,
+    mut breeder: Breeder,
+    automation: Res<Automation>,
+    clock: Res<GameClock>,
+    mut timer: ResMut<AutomationTimer>,
+    generations: Res<Generations>,
+    eggs: Query<(Entity, &Transform), With<Egg>>,
+    animals: Query<(Entity, &Animal), (Without<Picked>, Without<WalkingTogether>)>,
+) {
+    if !automation.auto_hatch && !automation.auto_merge {
+        return;
+    }
+    timer.0.tick(clock.delta());
+    if !timer.0.just_finished() {
+        return;
+    }
+    if automation.auto_hatch {
+        if let Some((egg, transform)) = eggs.iter().next() {
+            breeder.hatch(egg, *transform);
+        }
+    }
+    if automation.auto_merge {
+        let mut unpaired = HashMap::default();
+        for (entity, animal) in animals.iter() {
+            // otherwise the same pair would be sent off again every tick without ever merging
+            if generations
+                .breed(animal.generation, animal.generation)
+                .is_none()
+            {
+                continue;
+            }
+            if let Some(partner) = unpaired.remove(&animal.generation) {
+                commands.entity(entity).insert(WalkingTogether { partner });
+                commands
+                    .entity(partner)
+                    .insert(WalkingTogether { partner: entity });
+                return;
+            }
+            unpaired.insert(animal.generation, entity);
+        }
+    }
+}
+
+fn walk_together(
+    mut commands: Commands,
+    mut breeder: Breeder,
+    generations: Res<Generations>,
+    clock: Res<GameClock>,
+    mut walking: Query<(Entity, &mut Transform, &Animal, &WalkingTogether), Without<Picked>>,
+) {
+    let step = FIXED_TIMESTEP.as_secs_f32();
+    let steps = clock.fixed_steps().count() as f32;
+    let mut merged = vec![];
+    for (entity, transform, animal, walking_together) in walking.iter() {
+        if merged.contains(&entity) {
+            continue;
+        }
+        let (partner, partner_transform, partner_animal, _) =
+            match walking.get(walking_together.partner) {
+                Ok(partner) => partner,
+                // the partner was picked up by the player or merged with someone else
+                Err(_) => {
+                    commands.entity(entity).remove::<WalkingTogether>();
+                    continue;
+                }
+            };
+        let distance = transform
+            .translation
+            .truncate()
+            .distance(partner_transform.translation.truncate());
+        if distance < MERGE_DISTANCE {
+            let middle = transform
+                .translation
+                .lerp(partner_transform.translation, 0.5);
+            if !breeder.merge(
+                (entity, animal),
+                (partner, partner_animal),
+                Transform::from_translation(middle),
+            ) {
+                commands.entity(entity).remove::<WalkingTogether>();
+                commands.entity(partner).remove::<WalkingTogether>();
+            }
+            merged.push(entity);
+            merged.push(partner);
+        }
+    }
+    if steps == 0. {
+        return;
+    }
+    let targets: HashMap<Entity, Vec3> = walking
+        .iter()
+        .filter_map(|(entity, _, _, walking_together)| {
+            walking
+                .get(walking_together.partner)
+                .ok()
+                .map(|(_, partner_transform, _, _)| (entity, partner_transform.translation))
+        })
+        .collect();
+    for (entity, mut transform, animal, _) in walking.iter_mut() {
+        if merged.contains(&entity) {
+            continue;
+        }
+        let target = match targets.get(&entity) {
+            Some(target) => *target,
+            None => continue,
+        };
+        let speed = generations[animal.generation].speed * WALK_TOGETHER_SPEED_FACTOR;
+        // both walk, so each one only has to cover half of the distance
+        let towards_middle = (target - transform.translation).truncate() / 2.;
+        let walked = towards_middle.clamp_length_max(speed * step * steps);
+        transform.translation.x += walked.x;
+        transform.translation.y += walked.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::Actions;
+    use crate::animal::AnimalGeneration;
+    use crate::farm::{CurrentEggs, ANIMAL_Z};
+    use crate::loading::{read_generations, read_upgrades};
+    use crate::upgrades::UpgradeLevels;
+    use crate::SimulationPlugin;
+    use std::path::Path;
+
+    /// The game logic with the shipped data and the given upgrade levels bought
+    fn headless_app(bought: &[(&str, u32)]) -> App {
+        let generations = read_generations(Path::new("assets/animals.generations.ron")).unwrap();
+        let upgrades = read_upgrades(Path::new("assets/shop.upgrades.ron")).unwrap();
+        let levels = UpgradeLevels(
+            bought
+                .iter()
+                .map(|(id, level)| (upgrades.by_id(id).unwrap(), *level))
+                .collect(),
+        );
+        let mut app = App::new();
+        app.add_state(GameState::Playing)
+            .insert_resource(generations)
+            .insert_resource(upgrades)
+            .init_resource::<Actions>()
+            .add_plugin(SimulationPlugin)
+            .insert_resource(levels);
+
+        app
+    }
+
+    /// Run one frame per second of game time
+    fn advance(app: &mut App, seconds: u64) {
+        for _ in 0..seconds {
+            app.world
+                .resource_mut::<GameClock>()
+                .advance(Duration::from_secs(1));
+            app.update();
+        }
+    }
+
+    fn spawn_animal(app: &mut App, generation: AnimalGeneration, x: f32) {
+        let sprite = app.world.resource::<Generations>()[generation]
+            .sprite(Transform::from_xyz(x, 0., ANIMAL_Z));
+        app.world
+            .spawn()
+            .insert_bundle(sprite)
+            .insert(Animal::new(generation, 0.));
+    }
+
+    #[test]
+    fn auto_merge_only_pairs_generations_with_a_recipe() {
+        let mut app = headless_app(&[("auto_merge", 1)]);
+        let generations = app.world.resource::<Generations>().clone();
+        let dead_end = generations
+            .iter()
+            .map(|(generation, _)| generation)
+            .find(|generation| generations.breed(*generation, *generation).is_none())
+            .expect("the shipped generations should have one that cannot merge with itself");
+        let first = generations.first();
+        spawn_animal(&mut app, dead_end, -100.);
+        spawn_animal(&mut app, dead_end, 100.);
+        spawn_animal(&mut app, first, -200.);
+        spawn_animal(&mut app, first, 200.);
+
+        advance(&mut app, DEFAULT_AUTOMATION_INTERVAL as u64);
+
+        let walking: Vec<_> = app
+            .world
+            .query_filtered::<&Animal, With<WalkingTogether>>()
+            .iter(&app.world)
+            .map(|animal| animal.generation)
+            .collect();
+        assert_eq!(walking, vec![first, first]);
+    }
+
+    #[test]
+    fn auto_hatch_respects_max_eggs() {
+        // two eggs, one every second and one automation tick per second
+        let mut app = headless_app(&[
+            ("auto_hatch", 1),
+            ("max_eggs", 1),
+            ("faster_eggs", 9),
+            ("faster_automation", 8),
+        ]);
+
+        for _ in 0..30 {
+            advance(&mut app, 1);
+            let eggs = app
+                .world
+                .query_filtered::<(), With<Egg>>()
+                .iter(&app.world)
+                .count();
+            assert!(eggs <= 2, "{} eggs on the field", eggs);
+            assert_eq!(eggs, app.world.resource::<CurrentEggs>().0 as usize);
+        }
+        let hatched = app.world.query::<&Animal>().iter(&app.world).count();
+        assert!(hatched >= 10, "only {} eggs hatched", hatched);
+    }
+}
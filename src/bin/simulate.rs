@@ -0,0 +1,58 @@
+//! Plays the game headless at accelerated time and prints a CSV timeline for balancing
+//!
+//! `cargo run --bin simulate -- --duration 7200 --step 0.1 --report-every 60 > timeline.csv`
+
+use bevy::prelude::{App, MinimalPlugins};
+use shmoo_farm::{BalancingPlugin, GameRng};
+use std::env;
+use std::process;
+use std::time::Duration;
+
+fn main() {
+    let mut plugin = BalancingPlugin::default();
+    let mut rng = GameRng::default();
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .unwrap_or_else(|| usage(&format!("{} needs a value", flag)));
+        match flag.as_str() {
+            "--duration" => plugin.duration = seconds(&flag, &value),
+            "--step" => plugin.step = seconds(&flag, &value),
+            "--report-every" => plugin.report_interval = seconds(&flag, &value),
+            "--generations" => plugin.generations = value.into(),
+            "--upgrades" => plugin.upgrades = value.into(),
+            "--seed" => {
+                rng = GameRng::new(
+                    value
+                        .parse()
+                        .unwrap_or_else(|_| usage("--seed expects an unsigned integer")),
+                )
+            }
+            _ => usage(&format!("unknown argument {}", flag)),
+        }
+    }
+
+    // there is no log output under `MinimalPlugins`
+    eprintln!("Random seed: {}", rng.seed());
+    App::new()
+        .insert_resource(rng)
+        .add_plugins(MinimalPlugins)
+        .add_plugin(plugin)
+        .run();
+}
+
+fn seconds(flag: &str, value: &str) -> Duration {
+    match value.parse::<f64>() {
+        Ok(seconds) if seconds > 0. => Duration::from_secs_f64(seconds),
+        _ => usage(&format!("{} expects a positive number of seconds", flag)),
+    }
+}
+
+fn usage(error: &str) -> ! {
+    eprintln!("{}", error);
+    eprintln!(
+        "usage: simulate [--duration <seconds>] [--step <seconds>] [--report-every <seconds>] [--generations <path>] [--upgrades <path>] [--seed <number>]"
+    );
+    process::exit(2);
+}
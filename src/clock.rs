@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Length of one step of the logic that has to behave the same at any frame rate
+pub const FIXED_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// Time as seen by the game logic
+///
+/// The windowed game advances it with the real frame time, while the headless simulation
+/// advances it in fixed steps as fast as the machine allows.
+#[derive(Default)]
+pub struct GameClock {
+    delta: Duration,
+    elapsed: Duration,
+    /// Number of fixed timesteps that fit into `elapsed`
+    fixed_steps: u64,
+    /// Number of fixed timesteps that became due with the last `advance`
+    new_fixed_steps: u64,
+}
+
+impl GameClock {
+    pub fn advance(&mut self, delta: Duration) {
+        self.delta = delta;
+        self.elapsed += delta;
+        let fixed_steps = (self.elapsed.as_nanos() / FIXED_TIMESTEP.as_nanos()) as u64;
+        self.new_fixed_steps = fixed_steps - self.fixed_steps;
+        self.fixed_steps = fixed_steps;
+    }
+
+    /// Game time in seconds at the end of every fixed timestep that is due this frame
+    ///
+    /// Depending on the frame rate this yields no step at all or several of them.
+    pub fn fixed_steps(&self) -> impl Iterator<Item = f64> {
+        (self.fixed_steps - self.new_fixed_steps + 1..=self.fixed_steps)
+            .map(|step| step as f64 * FIXED_TIMESTEP.as_secs_f64())
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds_f64(&self) -> f64 {
+        self.delta.as_secs_f64()
+    }
+
+    pub fn seconds_since_startup(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+}
+
+pub fn follow_real_time(time: Res<Time>, mut clock: ResMut<GameClock>) {
+    clock.advance(time.delta());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(clock: &GameClock) -> Vec<f64> {
+        clock.fixed_steps().collect()
+    }
+
+    #[test]
+    fn starts_without_steps() {
+        let clock = GameClock::default();
+
+        assert!(steps(&clock).is_empty());
+        assert_eq!(clock.seconds_since_startup(), 0.);
+    }
+
+    #[test]
+    fn yields_every_step_that_became_due() {
+        let step = FIXED_TIMESTEP.as_secs_f64();
+        let mut clock = GameClock::default();
+        clock.advance(FIXED_TIMESTEP * 3);
+
+        assert_eq!(steps(&clock), vec![step, 2. * step, 3. * step]);
+        assert_eq!(clock.delta(), FIXED_TIMESTEP * 3);
+    }
+
+    #[test]
+    fn carries_partial_steps_over_to_the_next_frame() {
+        let step = FIXED_TIMESTEP.as_secs_f64();
+        let mut clock = GameClock::default();
+        let three_quarters = FIXED_TIMESTEP * 3 / 4;
+
+        clock.advance(three_quarters);
+        assert!(steps(&clock).is_empty());
+        clock.advance(three_quarters);
+        assert_eq!(steps(&clock), vec![step]);
+        clock.advance(three_quarters);
+        assert_eq!(steps(&clock), vec![2. * step]);
+        assert_eq!(
+            clock.seconds_since_startup(),
+            (three_quarters * 3).as_secs_f64()
+        );
+    }
+
+    #[test]
+    fn a_frame_without_time_has_no_steps() {
+        let mut clock = GameClock::default();
+        clock.advance(FIXED_TIMESTEP);
+        clock.advance(Duration::ZERO);
+
+        assert!(steps(&clock).is_empty());
+        assert_eq!(clock.delta_seconds_f64(), 0.);
+    }
+}
@@ -0,0 +1,14 @@
+use crate::money::Money;
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>();
+    }
+}
+
+#[derive(Default, Inspectable)]
+pub struct Score(pub Money);
@@ -0,0 +1,377 @@
+use crate::animal::{AnimalGeneration, Generations};
+use crate::events::{AnimalsMerged, EggHatched, IncomePaid};
+use crate::farm::ANIMAL_Z;
+use crate::loading::{FontAssets, TextureAssets};
+use crate::money::Money;
+use crate::settings::Settings;
+use crate::{GameState, MainCamera, ANIMAL_SIZE};
+use bevy::prelude::*;
+use rand::Rng;
+use std::f32::consts::TAU;
+
+/// Eye candy for hatching, merging and the income of the animals
+///
+/// Purely visual, so it uses the frame time and `thread_rng` and never touches the game logic.
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScreenShake>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(run_tweens)
+                    .with_system(move_particles)
+                    .with_system(shake_camera)
+                    .with_system(show_income)
+                    .with_system(float_income),
+            )
+            // the new animals only exist once the commands of the update stage are applied
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(hatch_effects)
+                    .with_system(merge_effects),
+            );
+    }
+}
+
+/// Effects are drawn in front of the animals
+const EFFECT_Z: f32 = ANIMAL_Z + 1.;
+const EGGSHELL_COLOR: Color = Color::rgb(0.96, 0.92, 0.8);
+/// Camera offset in pixels at full trauma
+const MAX_SHAKE_OFFSET: f32 = 8.;
+/// Trauma lost per second
+const SHAKE_DECAY: f32 = 1.5;
+const GRAVITY: f32 = -400.;
+/// Seconds an income number stays visible
+const FLOATING_INCOME_SECONDS: f32 = 1.;
+/// Pixels an income number rises while it fades out
+const FLOATING_INCOME_RISE: f32 = 40.;
+/// New income numbers are skipped while this many are shown
+const MAX_FLOATING_INCOME: usize = 100;
+
+/// How strong the effects of the given generation are, from 0 for the lowest income to 1 for
+/// the highest
+///
+/// Incomes grow by orders of magnitude, so they are compared on a logarithmic scale.
+fn intensity(generation: AnimalGeneration, generations: &Generations) -> f32 {
+    let incomes = generations
+        .iter()
+        .map(|(_, generation)| generation.money_per_second)
+        .filter(|income| *income > Money::ZERO)
+        .map(|income| income.log10());
+    let (lowest, highest) = incomes
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), income| {
+            (low.min(income), high.max(income))
+        });
+    let income = generations[generation].money_per_second;
+    if highest <= lowest || income <= Money::ZERO {
+        return 0.;
+    }
+
+    ((income.log10() - lowest) / (highest - lowest)).clamp(0., 1.) as f32
+}
+
+/// Shakes the main camera; trauma is added by effects and decays over time
+#[derive(Default)]
+pub struct ScreenShake {
+    trauma: f32,
+    /// Offset currently applied to the camera
+    offset: Vec2,
+}
+
+impl ScreenShake {
+    /// Offset currently applied to the camera; the camera's resting position is without it
+    pub fn offset(&self) -> Vec2 {
+        self.offset
+    }
+
+    pub fn add(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum TweenKind {
+    /// Grow from nothing, overshoot to `peak` times the size and settle at the original size
+    Pop { peak: f32 },
+    /// Wobble, grow and fade out like a bursting egg shell
+    Crack,
+}
+
+#[derive(Component)]
+struct Tween {
+    kind: TweenKind,
+    elapsed: f32,
+    duration: f32,
+    /// Remove the entity once the tween finished
+    despawn: bool,
+}
+
+impl Tween {
+    fn new(kind: TweenKind, duration: f32) -> Self {
+        Tween {
+            kind,
+            elapsed: 0.,
+            duration,
+            despawn: false,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+}
+
+fn hatch_effects(
+    mut commands: Commands,
+    mut hatched: EventReader<EggHatched>,
+    textures: Res<TextureAssets>,
+    existing: Query<(), With<Transform>>,
+    mut shake: ResMut<ScreenShake>,
+) {
+    for event in hatched.iter() {
+        if existing.contains(event.animal) {
+            commands
+                .entity(event.animal)
+                .insert(Tween::new(TweenKind::Pop { peak: 1.2 }, 0.35));
+        }
+        commands
+            .spawn_bundle(SpriteBundle {
+                texture: textures.egg.clone(),
+                transform: Transform::from_translation(event.position.extend(EFFECT_Z)),
+                ..default()
+            })
+            .insert(Tween {
+                despawn: true,
+                ..Tween::new(TweenKind::Crack, 0.3)
+            });
+        spawn_particles(&mut commands, event.position, 8, EGGSHELL_COLOR, 0.);
+        shake.add(0.15);
+    }
+}
+
+fn merge_effects(
+    mut commands: Commands,
+    mut merged: EventReader<AnimalsMerged>,
+    generations: Res<Generations>,
+    existing: Query<(), With<Transform>>,
+    mut shake: ResMut<ScreenShake>,
+) {
+    for event in merged.iter() {
+        let intensity = intensity(event.to, &generations);
+        if existing.contains(event.animal) {
+            commands.entity(event.animal).insert(Tween::new(
+                TweenKind::Pop {
+                    peak: 1.3 + 0.4 * intensity,
+                },
+                0.4 + 0.2 * intensity,
+            ));
+        }
+        spawn_particles(
+            &mut commands,
+            event.position,
+            10 + (30. * intensity) as usize,
+            generations[event.to].color,
+            intensity,
+        );
+        shake.add(0.25 + 0.5 * intensity);
+    }
+}
+
+fn spawn_particles(
+    commands: &mut Commands,
+    position: Vec2,
+    count: usize,
+    color: Color,
+    intensity: f32,
+) {
+    let mut rng = rand::thread_rng();
+    for _ in 0..count {
+        let angle = rng.gen_range(0. ..TAU);
+        let speed = rng.gen_range(60. ..160.) * (1. + intensity);
+        let size = rng.gen_range(4. ..7.);
+        commands
+            .spawn_bundle(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::splat(size)),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(EFFECT_Z)),
+                ..default()
+            })
+            .insert(Particle {
+                velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+                age: 0.,
+                lifetime: rng.gen_range(0.4..0.8),
+            });
+    }
+}
+
+fn run_tweens(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut tweens: Query<(Entity, &mut Tween, &mut Transform, Option<&mut Sprite>)>,
+) {
+    for (entity, mut tween, mut transform, sprite) in tweens.iter_mut() {
+        tween.elapsed += time.delta_seconds();
+        let progress = (tween.elapsed / tween.duration).min(1.);
+        match tween.kind {
+            TweenKind::Pop { peak } => {
+                // the first part grows to the peak, the rest settles back
+                let grow = 0.6;
+                let scale = if progress < grow {
+                    ease_out(progress / grow) * peak
+                } else {
+                    peak + (1. - peak) * ease_out((progress - grow) / (1. - grow))
+                };
+                transform.scale = Vec3::new(scale, scale, 1.);
+            }
+            TweenKind::Crack => {
+                let wobble = (progress * 40.).sin() * 0.3 * (1. - progress);
+                transform.rotation = Quat::from_rotation_z(wobble);
+                transform.scale = Vec3::new(1. + 0.5 * progress, 1. + 0.5 * progress, 1.);
+                if let Some(mut sprite) = sprite {
+                    sprite.color.set_a(1. - progress);
+                }
+            }
+        }
+        if progress >= 1. {
+            if tween.despawn {
+                commands.entity(entity).despawn();
+            } else {
+                commands.entity(entity).remove::<Tween>();
+            }
+        }
+    }
+}
+
+fn ease_out(progress: f32) -> f32 {
+    1. - (1. - progress).powi(2)
+}
+
+fn move_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite) in particles.iter_mut() {
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        particle.velocity.y += GRAVITY * delta;
+        transform.translation += (particle.velocity * delta).extend(0.);
+        sprite.color.set_a(1. - particle.age / particle.lifetime);
+    }
+}
+
+fn shake_camera(
+    time: Res<Time>,
+    mut shake: ResMut<ScreenShake>,
+    mut camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if shake.trauma <= 0. && shake.offset == Vec2::ZERO {
+        return;
+    }
+    shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.);
+    let offset = if shake.trauma > 0. {
+        let mut rng = rand::thread_rng();
+        let direction = Vec2::new(rng.gen_range(-1. ..1.), rng.gen_range(-1. ..1.));
+        direction * MAX_SHAKE_OFFSET * shake.trauma.powi(2)
+    } else {
+        Vec2::ZERO
+    };
+    for mut transform in camera.iter_mut() {
+        transform.translation += (offset - shake.offset).extend(0.);
+    }
+    shake.offset = offset;
+}
+
+#[derive(Component)]
+struct FloatingIncome {
+    age: f32,
+}
+
+fn show_income(
+    mut commands: Commands,
+    mut paid: EventReader<IncomePaid>,
+    settings: Res<Settings>,
+    font_assets: Res<FontAssets>,
+    floating: Query<(), With<FloatingIncome>>,
+) {
+    if !settings.floating_income {
+        // drop the events of this frame instead of showing them once enabled again
+        paid.iter().last();
+        return;
+    }
+    let mut shown = floating.iter().count();
+    for income in paid.iter() {
+        if shown >= MAX_FLOATING_INCOME {
+            continue;
+        }
+        shown += 1;
+        let position = income.position + Vec2::new(0., ANIMAL_SIZE / 2.);
+        commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    format!("+{} G", settings.number_format.format(income.amount)),
+                    TextStyle {
+                        font: font_assets.fira_sans.clone(),
+                        font_size: 20.,
+                        color: Color::rgb(1., 0.85, 0.2),
+                    },
+                    TextAlignment {
+                        vertical: VerticalAlign::Center,
+                        horizontal: HorizontalAlign::Center,
+                    },
+                ),
+                transform: Transform::from_translation(position.extend(EFFECT_Z)),
+                ..default()
+            })
+            .insert(FloatingIncome { age: 0. });
+    }
+}
+
+fn float_income(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut floating: Query<(Entity, &mut FloatingIncome, &mut Transform, &mut Text)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut income, mut transform, mut text) in floating.iter_mut() {
+        income.age += delta;
+        if income.age >= FLOATING_INCOME_SECONDS {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation.y += FLOATING_INCOME_RISE / FLOATING_INCOME_SECONDS * delta;
+        text.sections[0]
+            .style
+            .color
+            .set_a(1. - income.age / FLOATING_INCOME_SECONDS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loading::read_generations;
+    use std::path::Path;
+
+    #[test]
+    fn intensity_grows_with_income() {
+        let generations = read_generations(Path::new("assets/animals.generations.ron")).unwrap();
+        let intensity = |name| intensity(generations.by_name(name).unwrap(), &generations);
+
+        assert_eq!(intensity("Chicken"), 0.);
+        assert_eq!(intensity("ChickenDuckGoatSheepPigCowRabbit"), 1.);
+        assert!(intensity("GoldenChicken") < intensity("WoollyDuck"));
+    }
+}
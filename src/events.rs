@@ -0,0 +1,67 @@
+//! Typed events describing what happened on the farm
+//!
+//! Gameplay systems only send them; audio, statistics, achievements and effects subscribe
+//! to the ones they care about instead of reaching into each other's resources.
+
+use crate::animal::AnimalGeneration;
+use crate::money::Money;
+use crate::upgrades::UpgradeId;
+use bevy::prelude::*;
+
+pub struct GameEventsPlugin;
+
+impl Plugin for GameEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EggSpawned>()
+            .add_event::<EggHatched>()
+            .add_event::<AnimalsMerged>()
+            .add_event::<MergeRejected>()
+            .add_event::<UpgradePurchased>()
+            .add_event::<MoneyEarned>()
+            .add_event::<IncomePaid>();
+    }
+}
+
+/// A new egg appeared on the playfield; eggs restored from a save game do not count
+pub struct EggSpawned {
+    pub position: Vec2,
+}
+
+pub struct EggHatched {
+    /// The newly spawned animal
+    pub animal: Entity,
+    pub generation: AnimalGeneration,
+    pub position: Vec2,
+}
+
+pub struct AnimalsMerged {
+    /// The child that replaced both parents
+    pub animal: Entity,
+    pub from: (AnimalGeneration, AnimalGeneration),
+    pub to: AnimalGeneration,
+    pub position: Vec2,
+}
+
+/// The player dropped an animal on another one, but there is no recipe for the pair
+pub struct MergeRejected {
+    pub generations: (AnimalGeneration, AnimalGeneration),
+    pub position: Vec2,
+}
+
+pub struct UpgradePurchased {
+    pub upgrade: UpgradeId,
+    /// Level of the upgrade after the purchase
+    pub level: u32,
+    pub price: Money,
+}
+
+/// Income of the farm paid this tick, after the prestige multiplier
+pub struct MoneyEarned(pub Money);
+
+/// One animal paid out its income; sent once per income tick of every animal
+pub struct IncomePaid {
+    pub animal: Entity,
+    /// After the prestige multiplier
+    pub amount: Money,
+    pub position: Vec2,
+}
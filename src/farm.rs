@@ -1,14 +1,20 @@
-use crate::animal::{Animal, Picked};
-use crate::loading::TextureAssets;
-use crate::ui::Score;
-use crate::{GameState, ANIMAL_SIZE, UI_WIDTH, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::animal::{Animal, Generations};
+use crate::clock::GameClock;
+use crate::economy::Score;
+use crate::events::{EggSpawned, IncomePaid, MoneyEarned};
+use crate::money::Money;
+use crate::playfield::Playfield;
+use crate::prestige::Prestige;
+use crate::random::GameRng;
+use crate::GameState;
 use bevy::prelude::*;
 use bevy_inspector_egui::Inspectable;
-use rand::random;
 use std::time::Duration;
 
 pub const BACKGROUND_Z: f32 = 0.;
 pub const ANIMAL_Z: f32 = 1.;
+/// Animals pay out their income in ticks of this many seconds
+pub const INCOME_TICK_SECONDS: f64 = 1.;
 
 pub struct FarmPlugin;
 
@@ -18,24 +24,16 @@ impl Plugin for FarmPlugin {
             .init_resource::<CurrentEggs>()
             .init_resource::<CurrentMaxEggs>()
             .init_resource::<CurrentEggTime>()
-            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(draw_background))
             .add_system_set(
                 SystemSet::on_update(GameState::Playing)
                     .with_system(spawn)
                     .with_system(collect_money)
-                    .with_system(update_spawner_timer),
+                    .with_system(update_spawner_timer)
+                    .with_system(keep_inside_playfield),
             );
     }
 }
 
-fn draw_background(mut commands: Commands, textures: Res<TextureAssets>) {
-    commands.spawn_bundle(SpriteBundle {
-        transform: Transform::from_xyz(0., 0., BACKGROUND_Z),
-        texture: textures.background.clone(),
-        ..default()
-    });
-}
-
 pub struct CurrentEggs(pub u8);
 
 impl Default for CurrentEggs {
@@ -83,30 +81,43 @@ fn update_spawner_timer(current_egg_time: Res<CurrentEggTime>, mut timer: ResMut
 
 fn spawn(
     mut commands: Commands,
-    textures: Res<TextureAssets>,
     mut timer: ResMut<SpawnEggTimer>,
     mut current_eggs: ResMut<CurrentEggs>,
     current_max_eggs: Res<CurrentMaxEggs>,
-    time: Res<Time>,
+    clock: Res<GameClock>,
+    prestige: Res<Prestige>,
+    playfield: Res<Playfield>,
+    mut rng: ResMut<GameRng>,
+    mut spawned: EventWriter<EggSpawned>,
 ) {
     if current_max_eggs.0 <= current_eggs.0 {
         return;
     }
-    timer.0.tick(time.delta());
+    timer
+        .0
+        .tick(clock.delta().mul_f64(prestige.egg_speed_multiplier()));
     if !timer.0.just_finished() {
         return;
     }
     current_eggs.0 += 1;
     timer.0.reset();
 
+    let translation = random_egg_translation(&playfield, &mut rng);
+    spawn_egg(&mut commands, translation);
+    spawned.send(EggSpawned {
+        position: translation.truncate(),
+    });
+}
+
+pub fn random_egg_translation(playfield: &Playfield, rng: &mut GameRng) -> Vec3 {
+    playfield.random_position(rng).extend(ANIMAL_Z)
+}
+
+/// The texture is added by the graphics, so eggs can also be spawned without any loaded assets
+pub fn spawn_egg(commands: &mut Commands, translation: Vec3) {
     commands
         .spawn_bundle(SpriteBundle {
-            texture: textures.egg.clone(),
-            transform: Transform::from_xyz(
-                (random::<f32>() - 0.5) * (WINDOW_WIDTH - ANIMAL_SIZE - UI_WIDTH) - UI_WIDTH / 2.,
-                (random::<f32>() - 0.5) * (WINDOW_HEIGHT - ANIMAL_SIZE),
-                ANIMAL_Z,
-            ),
+            transform: Transform::from_translation(translation),
             ..default()
         })
         .insert(Egg);
@@ -115,23 +126,127 @@ fn spawn(
 #[derive(Component)]
 pub struct Egg;
 
-pub fn get_animal_in_reach(
-    animals: &Query<(Entity, &Transform, &Animal), Without<Picked>>,
-    position: &Vec2,
-    reach: f32,
-) -> Option<Entity> {
-    for (entity, transform, _) in animals.iter() {
-        let animal_position = Vec2::new(transform.translation.x, transform.translation.y);
-        if animal_position.distance(*position) < reach {
-            return Some(entity);
+/// Every animal pays its income once per tick, counted from the moment it appeared
+fn collect_money(
+    mut score: ResMut<Score>,
+    prestige: Res<Prestige>,
+    mut animals: Query<(Entity, &Transform, &mut Animal)>,
+    generations: Res<Generations>,
+    clock: Res<GameClock>,
+    mut earnings: EventWriter<MoneyEarned>,
+    mut paid: EventWriter<IncomePaid>,
+) {
+    let mut earned = Money::ZERO;
+    for (entity, transform, mut animal) in animals.iter_mut() {
+        animal.unpaid_seconds += clock.delta_seconds_f64();
+        // the headless simulation may advance by more than one tick per frame
+        let ticks = (animal.unpaid_seconds / INCOME_TICK_SECONDS).floor();
+        if ticks < 1. {
+            continue;
         }
+        animal.unpaid_seconds -= ticks * INCOME_TICK_SECONDS;
+        let amount = generations[animal.generation].money_per_second
+            * prestige.income_multiplier()
+            * (ticks * INCOME_TICK_SECONDS);
+        earned += amount;
+        paid.send(IncomePaid {
+            animal: entity,
+            amount,
+            position: transform.translation.truncate(),
+        });
+    }
+    if earned == Money::ZERO {
+        return;
     }
+    score.0 += earned;
+    earnings.send(MoneyEarned(earned));
+}
 
-    None
+/// Money per second the given animals earn together
+pub fn income<'a>(
+    animals: impl Iterator<Item = &'a Animal>,
+    generations: &Generations,
+    prestige: &Prestige,
+) -> Money {
+    let base: Money = animals
+        .map(|animal| generations[animal.generation].money_per_second)
+        .sum();
+
+    base * prestige.income_multiplier()
 }
 
-fn collect_money(mut score: ResMut<Score>, animals: Query<&Animal>, time: Res<Time>) {
-    animals.iter().for_each(|animal| {
-        score.0 += animal.generation.money_per_second() * time.delta().as_secs_f32()
-    });
+/// Pull everything back into view when the window shrinks
+fn keep_inside_playfield(
+    playfield: Res<Playfield>,
+    mut placed: Query<&mut Transform, Or<(With<Animal>, With<Egg>)>>,
+) {
+    if !playfield.is_changed() {
+        return;
+    }
+    for mut transform in placed.iter_mut() {
+        let position = playfield.clamp(transform.translation.truncate());
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loading::read_generations;
+    use std::path::Path;
+
+    /// Only `collect_money`, with one chicken on the farm
+    fn farm_with_a_chicken() -> App {
+        let generations = read_generations(Path::new("assets/animals.generations.ron")).unwrap();
+        let chicken = generations.first();
+        let mut app = App::new();
+        app.add_event::<MoneyEarned>()
+            .add_event::<IncomePaid>()
+            .init_resource::<Score>()
+            .init_resource::<Prestige>()
+            .init_resource::<GameClock>()
+            .insert_resource(generations)
+            .add_system(collect_money);
+        app.world
+            .spawn()
+            .insert(Transform::default())
+            .insert(Animal::new(chicken, 0.));
+
+        app
+    }
+
+    fn advance(app: &mut App, seconds: f64) {
+        app.world
+            .resource_mut::<GameClock>()
+            .advance(Duration::from_secs_f64(seconds));
+        app.update();
+    }
+
+    /// The score should hold the chicken's income of this many ticks
+    fn assert_paid(app: &App, ticks: f64) {
+        let generations = app.world.resource::<Generations>();
+        let expected = generations[generations.first()].money_per_second.to_f64()
+            * INCOME_TICK_SECONDS
+            * ticks;
+        let score = app.world.resource::<Score>().0.to_f64();
+
+        assert!((score - expected).abs() < 1e-9, "{} != {}", score, expected);
+    }
+
+    #[test]
+    fn animals_pay_once_per_tick() {
+        let mut app = farm_with_a_chicken();
+
+        advance(&mut app, INCOME_TICK_SECONDS / 2.);
+        assert_paid(&app, 0.);
+        advance(&mut app, INCOME_TICK_SECONDS / 2.);
+        assert_paid(&app, 1.);
+
+        // a long frame pays every tick it covers and keeps the remainder
+        advance(&mut app, INCOME_TICK_SECONDS * 2.5);
+        assert_paid(&app, 3.);
+        advance(&mut app, INCOME_TICK_SECONDS / 2.);
+        assert_paid(&app, 4.);
+    }
 }
@@ -0,0 +1,139 @@
+use crate::animal::{Animal, AnimalState, AnimationClips, Generations, Picked};
+use crate::farm::{Egg, BACKGROUND_Z};
+use crate::loading::TextureAssets;
+use crate::playfield::Playfield;
+use crate::GameState;
+use bevy::prelude::*;
+
+/// Textures for everything the game logic spawns
+///
+/// The logic itself stays free of loaded assets, so it can also run headless.
+pub struct GraphicsPlugin;
+
+impl Plugin for GraphicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::Playing).with_system(draw_background))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(fit_background))
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(texture_eggs)
+                    .with_system(start_animations)
+                    .with_system(animate_animals.after(start_animations)),
+            );
+    }
+}
+
+#[derive(Component)]
+struct Background;
+
+fn draw_background(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    playfield: Res<Playfield>,
+) {
+    commands
+        .spawn_bundle(SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(playfield.window_size()),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., BACKGROUND_Z),
+            texture: textures.background.clone(),
+            ..default()
+        })
+        .insert(Background);
+}
+
+/// Stretch the background over the whole window
+fn fit_background(playfield: Res<Playfield>, mut background: Query<&mut Sprite, With<Background>>) {
+    if !playfield.is_changed() {
+        return;
+    }
+    for mut sprite in background.iter_mut() {
+        sprite.custom_size = Some(playfield.window_size());
+    }
+}
+
+fn texture_eggs(mut eggs: Query<&mut Handle<Image>, Added<Egg>>, textures: Res<TextureAssets>) {
+    for mut texture in eggs.iter_mut() {
+        *texture = textures.egg.clone();
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Clip {
+    Hatch,
+    Idle,
+    Walk,
+    Picked,
+}
+
+impl Clip {
+    fn frames(self, clips: &AnimationClips) -> &[usize] {
+        match self {
+            Clip::Hatch => &clips.hatch,
+            Clip::Idle => &clips.idle,
+            Clip::Walk => &clips.walk,
+            Clip::Picked => &clips.picked,
+        }
+    }
+}
+
+#[derive(Component)]
+struct Animation {
+    clip: Clip,
+    /// Seconds since the clip started
+    elapsed: f32,
+}
+
+fn start_animations(mut commands: Commands, animals: Query<Entity, Added<Animal>>) {
+    for entity in animals.iter() {
+        commands.entity(entity).insert(Animation {
+            clip: Clip::Hatch,
+            elapsed: 0.,
+        });
+    }
+}
+
+/// Pick the clip matching what the animal is doing and turn it towards where it walks
+fn animate_animals(
+    time: Res<Time>,
+    generations: Res<Generations>,
+    mut animals: Query<(
+        &Animal,
+        &mut Animation,
+        &mut TextureAtlasSprite,
+        Option<&Picked>,
+    )>,
+) {
+    for (animal, mut animation, mut sprite, picked) in animals.iter_mut() {
+        let clips = &generations[animal.generation].animations;
+        animation.elapsed += time.delta_seconds();
+        let frame = (animation.elapsed * clips.fps) as usize;
+        let hatching = animation.clip == Clip::Hatch && frame < Clip::Hatch.frames(clips).len();
+        let clip = if picked.is_some() {
+            Clip::Picked
+        } else if hatching {
+            Clip::Hatch
+        } else {
+            match animal.state {
+                AnimalState::Idle { .. } => Clip::Idle,
+                AnimalState::Moving { .. } => Clip::Walk,
+            }
+        };
+        if clip != animation.clip {
+            animation.clip = clip;
+            animation.elapsed = 0.;
+        }
+
+        let frames = clip.frames(clips);
+        let frame = (animation.elapsed * clips.fps) as usize;
+        sprite.index = frames[frame % frames.len()];
+        if let AnimalState::Moving { velocity, .. } = animal.state {
+            if velocity.x != 0. {
+                sprite.flip_x = velocity.x < 0.;
+            }
+        }
+    }
+}
@@ -1,17 +1,56 @@
+mod achievements;
 mod actions;
 mod animal;
 mod audio;
+mod automation;
+mod clock;
+mod economy;
+mod effects;
+mod events;
 mod farm;
+mod graphics;
 mod loading;
+mod loading_screen;
+mod menu;
+mod money;
+mod music;
+mod number_format;
+mod playfield;
+mod prestige;
+mod random;
+mod save;
+mod settings;
+mod simulation;
+mod spatial;
+mod statistics;
 mod ui;
+mod upgrades;
 
 use crate::audio::InternalAudioPlugin;
 use crate::loading::LoadingPlugin;
+use crate::loading_screen::LoadingScreenPlugin;
 
+use crate::achievements::AchievementsPlugin;
 use crate::actions::ActionPlugin;
 use crate::animal::AnimalPlugin;
+use crate::automation::AutomationPlugin;
+use crate::clock::{follow_real_time, GameClock};
+use crate::economy::EconomyPlugin;
+use crate::effects::EffectsPlugin;
+use crate::events::GameEventsPlugin;
 use crate::farm::FarmPlugin;
+use crate::graphics::GraphicsPlugin;
+use crate::menu::MenuPlugin;
+use crate::music::MusicPlugin;
+use crate::playfield::{fit_playfield_to_window, Playfield};
+use crate::prestige::PrestigePlugin;
+use crate::random::log_seed;
+use crate::save::SavePlugin;
+use crate::settings::SettingsPlugin;
+use crate::spatial::SpatialPlugin;
+use crate::statistics::StatisticsPlugin;
 use crate::ui::UiPlugin;
+use crate::upgrades::UpgradesPlugin;
 use bevy::app::App;
 #[cfg(debug_assertions)]
 use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
@@ -19,15 +58,24 @@ use bevy::prelude::*;
 #[cfg(debug_assertions)]
 use bevy_inspector_egui::{InspectorPlugin, WorldInspectorPlugin};
 
+pub use crate::random::GameRng;
+pub use crate::simulation::BalancingPlugin;
+
 pub const WINDOW_WIDTH: f32 = 800.;
-pub const UI_WIDTH: f32 = 200.;
 pub const WINDOW_HEIGHT: f32 = 600.;
 pub const ANIMAL_SIZE: f32 = 64.;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 enum GameState {
     Loading,
+    /// An asset could not be loaded; the game cannot start
+    LoadingFailed,
+    MainMenu,
     Playing,
+    /// Pushed on top of `Playing`, so the farm stands still until the game is resumed
+    Paused,
+    /// Pushed on top of the menu it was opened from
+    Settings,
 }
 
 pub struct GamePlugin;
@@ -36,28 +84,68 @@ impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.add_state(GameState::Loading)
             .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(prepare))
+            .add_system_set_to_stage(
+                CoreStage::PreUpdate,
+                SystemSet::on_update(GameState::Playing).with_system(follow_real_time),
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, fit_playfield_to_window)
             .add_plugin(LoadingPlugin)
-            .add_plugin(FarmPlugin)
-            .add_plugin(AnimalPlugin)
+            .add_plugin(LoadingScreenPlugin)
+            .add_plugin(SimulationPlugin)
+            .add_plugin(GraphicsPlugin)
+            .add_plugin(EffectsPlugin)
             .add_plugin(InternalAudioPlugin)
+            .add_plugin(MusicPlugin)
             .add_plugin(UiPlugin)
-            .add_plugin(ActionPlugin);
+            .add_plugin(MenuPlugin)
+            .add_plugin(ActionPlugin)
+            .add_plugin(AchievementsPlugin)
+            .add_plugin(SavePlugin)
+            .add_plugin(SettingsPlugin);
 
         #[cfg(debug_assertions)]
         {
             app.add_plugin(FrameTimeDiagnosticsPlugin::default())
                 .add_plugin(LogDiagnosticsPlugin::default())
-                .add_plugin(InspectorPlugin::<ui::Score>::new())
+                .add_plugin(InspectorPlugin::<economy::Score>::new())
                 .add_plugin(InspectorPlugin::<farm::CurrentMaxEggs>::new())
                 .add_plugin(InspectorPlugin::<farm::CurrentEggTime>::new())
+                .add_plugin(InspectorPlugin::<prestige::Prestige>::new())
+                .add_plugin(InspectorPlugin::<settings::Settings>::new())
                 .add_plugin(WorldInspectorPlugin::new());
         }
     }
 }
 
+/// The game logic without any window, input, textures or audio
+///
+/// Expects [`GameState`] and the [`animal::Generations`] and [`upgrades::Upgrades`] resources
+/// to be provided by the app.
+/// Insert a [`GameRng`] before adding the plugin to run with a fixed seed.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>()
+            .init_resource::<GameRng>()
+            .init_resource::<Playfield>()
+            .add_startup_system(log_seed)
+            .add_plugin(GameEventsPlugin)
+            .add_plugin(StatisticsPlugin)
+            .add_plugin(FarmPlugin)
+            .add_plugin(SpatialPlugin)
+            .add_plugin(AnimalPlugin)
+            .add_plugin(EconomyPlugin)
+            .add_plugin(PrestigePlugin)
+            .add_plugin(AutomationPlugin)
+            .add_plugin(UpgradesPlugin);
+    }
+}
+
 #[derive(SystemLabel, Clone, Hash, Debug, Eq, PartialEq)]
 pub enum ShmooLabels {
     ProcessActions,
+    IndexPlacements,
 }
 
 #[derive(Component)]
@@ -1,72 +1,839 @@
-use crate::GameState;
+use crate::achievements::Achievements;
+use crate::animal::{AnimalGeneration, AnimationClips, Generation, Generations, SpriteSheet};
+use crate::money::Money;
+use crate::music::{MusicLayer, MusicLayers};
+use crate::upgrades::Upgrades;
+use crate::{GameState, ANIMAL_SIZE};
+use anyhow::{anyhow, bail};
+use bevy::asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext, LoadedAsset};
 use bevy::prelude::*;
-use bevy_asset_loader::{AssetCollection, AssetLoader};
+use bevy::utils::HashMap;
+use bevy_asset_loader::{AssetCollection, AssetLoader as AssetCollectionLoader};
 use bevy_kira_audio::AudioSource;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Declares files under keys, so data files can reference them without knowing their path
+const DYNAMIC_ASSETS: &str = "dynamic.assets";
 
 pub struct LoadingPlugin;
 
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
-        AssetLoader::new(GameState::Loading)
-            .with_dynamic_asset_collection_file("dynamic.assets")
+        app.add_asset::<Generations>()
+            .init_asset_loader::<GenerationsLoader>()
+            .add_asset::<Upgrades>()
+            .init_asset_loader::<UpgradesLoader>()
+            .add_asset::<Achievements>()
+            .init_asset_loader::<AchievementsLoader>()
+            .add_asset::<MusicLayers>()
+            .init_asset_loader::<MusicLayersLoader>()
+            .add_system_set(
+                SystemSet::on_exit(GameState::Loading)
+                    .with_system(insert_generations)
+                    .with_system(insert_upgrades)
+                    .with_system(insert_achievements)
+                    .with_system(insert_music_layers),
+            );
+        AssetCollectionLoader::new(GameState::Loading)
+            .with_dynamic_asset_collection_file(DYNAMIC_ASSETS)
             .with_collection::<FontAssets>()
             .with_collection::<AudioAssets>()
             .with_collection::<TextureAssets>()
-            .continue_to_state(GameState::Playing)
+            .with_collection::<DataAssets>()
+            .continue_to_state(GameState::MainMenu)
             .build(app);
     }
 }
 
-#[derive(AssetCollection)]
-pub struct FontAssets {
-    #[asset(path = "fonts/FiraSans-Bold.ttf")]
-    pub fira_sans: Handle<Font>,
-}
-
-#[derive(AssetCollection)]
-pub struct AudioAssets {
-    #[asset(path = "audio/background.ogg")]
-    pub background: Handle<AudioSource>,
-    #[asset(path = "audio/chicken.ogg")]
-    pub chicken: Handle<AudioSource>,
-    #[asset(path = "audio/duck.ogg")]
-    pub duck: Handle<AudioSource>,
-    #[asset(path = "audio/goat.ogg")]
-    pub goat: Handle<AudioSource>,
-    #[asset(path = "audio/sheep.ogg")]
-    pub sheep: Handle<AudioSource>,
-    #[asset(path = "audio/pig.ogg")]
-    pub pig: Handle<AudioSource>,
-    #[asset(path = "audio/cow.ogg")]
-    pub cow: Handle<AudioSource>,
-    #[asset(path = "audio/yipee.ogg")]
-    pub yipee: Handle<AudioSource>,
-}
-
-#[derive(AssetCollection)]
-pub struct TextureAssets {
-    #[asset(key = "background")]
-    pub background: Handle<Image>,
-    #[asset(key = "question_mark")]
-    pub question_mark: Handle<Image>,
-    #[asset(key = "coin")]
-    pub coin: Handle<Image>,
-    #[asset(key = "egg")]
-    pub egg: Handle<Image>,
-    #[asset(key = "egg_timer")]
-    pub egg_timer: Handle<Image>,
-    #[asset(key = "chicken")]
-    pub chicken: Handle<Image>,
-    #[asset(key = "chicken_2")]
-    pub chicken_2: Handle<Image>,
-    #[asset(key = "chicken_3")]
-    pub chicken_3: Handle<Image>,
-    #[asset(key = "chicken_4")]
-    pub chicken_4: Handle<Image>,
-    #[asset(key = "chicken_5")]
-    pub chicken_5: Handle<Image>,
-    #[asset(key = "chicken_6")]
-    pub chicken_6: Handle<Image>,
-    #[asset(key = "chicken_7")]
-    pub chicken_7: Handle<Image>,
+/// Declares an asset collection loaded from keys in `dynamic.assets`
+///
+/// Every collection also lists its keys in `KEYS`, so the loading screen can track it.
+macro_rules! keyed_collection {
+    (
+        pub struct $name:ident {
+            $($(#[doc = $doc:literal])* $key:tt => pub $field:ident: $type:ty,)*
+        }
+    ) => {
+        #[derive(AssetCollection)]
+        pub struct $name {
+            $(
+                $(#[doc = $doc])*
+                #[asset(key = $key)]
+                pub $field: $type,
+            )*
+        }
+
+        impl $name {
+            /// Keys in `dynamic.assets` of all assets in this collection
+            pub const KEYS: &'static [&'static str] = &[$($key),*];
+        }
+    };
+}
+
+keyed_collection! {
+    pub struct FontAssets {
+        "fira_sans" => pub fira_sans: Handle<Font>,
+    }
+}
+
+keyed_collection! {
+    pub struct AudioAssets {
+        /// Background music; the tracks are loaded as dependencies of this file
+        "music" => pub music: Handle<MusicLayers>,
+    }
+}
+
+keyed_collection! {
+    pub struct TextureAssets {
+        "background" => pub background: Handle<Image>,
+        "question_mark" => pub question_mark: Handle<Image>,
+        "coin" => pub coin: Handle<Image>,
+        "egg" => pub egg: Handle<Image>,
+        "egg_timer" => pub egg_timer: Handle<Image>,
+    }
+}
+
+keyed_collection! {
+    pub struct DataAssets {
+        "generations" => pub generations: Handle<Generations>,
+        "upgrades" => pub upgrades: Handle<Upgrades>,
+        "achievements" => pub achievements: Handle<Achievements>,
+    }
+}
+
+fn insert_generations(
+    mut commands: Commands,
+    data_assets: Res<DataAssets>,
+    generations: Res<Assets<Generations>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let mut generations = generations
+        .get(&data_assets.generations)
+        .expect("Animal generations should be loaded")
+        .clone();
+    for generation in generations.iter_mut() {
+        let sheet = &generation.sheet;
+        generation.atlas = texture_atlases.add(TextureAtlas::from_grid(
+            sheet.texture.clone(),
+            sheet.tile_size,
+            sheet.columns,
+            sheet.rows,
+        ));
+    }
+    commands.insert_resource(generations);
+}
+
+fn insert_upgrades(
+    mut commands: Commands,
+    data_assets: Res<DataAssets>,
+    upgrades: Res<Assets<Upgrades>>,
+) {
+    let upgrades = upgrades
+        .get(&data_assets.upgrades)
+        .expect("Upgrades should be loaded")
+        .clone();
+    commands.insert_resource(upgrades);
+}
+
+fn insert_achievements(
+    mut commands: Commands,
+    data_assets: Res<DataAssets>,
+    achievements: Res<Assets<Achievements>>,
+) {
+    let achievements = achievements
+        .get(&data_assets.achievements)
+        .expect("Achievements should be loaded")
+        .clone();
+    commands.insert_resource(achievements);
+}
+
+fn insert_music_layers(
+    mut commands: Commands,
+    audio_assets: Res<AudioAssets>,
+    music_layers: Res<Assets<MusicLayers>>,
+) {
+    let music_layers = music_layers
+        .get(&audio_assets.music)
+        .expect("Music layers should be loaded")
+        .clone();
+    commands.insert_resource(music_layers);
+}
+
+/// Read generations without an asset server; their image and sound handles stay empty
+///
+/// The asset keys are resolved against the `dynamic.assets` next to the generations file.
+pub fn read_generations(path: &Path) -> anyhow::Result<Generations> {
+    let definition: GenerationsDefinition = ron::de::from_bytes(&fs::read(path)?)?;
+    let declared: DeclaredAssets =
+        ron::de::from_bytes(&fs::read(path.with_file_name(DYNAMIC_ASSETS))?)?;
+
+    build_generations(definition, &declared, |_| Default::default())
+}
+
+/// Content of `dynamic.assets`
+#[derive(Deserialize)]
+pub(crate) struct DeclaredAssets(HashMap<String, DeclaredAsset>);
+
+#[derive(Deserialize)]
+enum DeclaredAsset {
+    File {
+        path: String,
+    },
+    TextureAtlas {
+        path: String,
+        tile_size_x: f32,
+        tile_size_y: f32,
+        columns: usize,
+        rows: usize,
+    },
+}
+
+impl DeclaredAssets {
+    fn get(&self, key: &str, referenced_by: &str) -> anyhow::Result<&DeclaredAsset> {
+        self.0
+            .get(key)
+            .ok_or_else(|| anyhow!("{} references unknown asset key '{}'", referenced_by, key))
+    }
+
+    /// Asset path of the file declared under the given key
+    pub(crate) fn file(&self, key: &str, referenced_by: &str) -> anyhow::Result<&str> {
+        match self.get(key, referenced_by)? {
+            DeclaredAsset::File { path } => Ok(path),
+            DeclaredAsset::TextureAtlas { .. } => {
+                bail!("{} expects a file under key '{}'", referenced_by, key)
+            }
+        }
+    }
+
+    /// Image path and grid of the sprite sheet declared under the given key
+    fn sprite_sheet(&self, key: &str, referenced_by: &str) -> anyhow::Result<SheetFile> {
+        match self.get(key, referenced_by)? {
+            DeclaredAsset::TextureAtlas {
+                path,
+                tile_size_x,
+                tile_size_y,
+                columns,
+                rows,
+            } => Ok(SheetFile {
+                path,
+                tile_size: Vec2::new(*tile_size_x, *tile_size_y),
+                columns: *columns,
+                rows: *rows,
+            }),
+            DeclaredAsset::File { .. } => {
+                bail!(
+                    "{} expects a sprite sheet under key '{}'",
+                    referenced_by,
+                    key
+                )
+            }
+        }
+    }
+}
+
+/// Content of an `*.generations.ron` file
+#[derive(Deserialize)]
+struct GenerationsDefinition {
+    generations: Vec<GenerationDefinition>,
+    #[serde(default)]
+    recipes: Vec<RecipeDefinition>,
+}
+
+#[derive(Deserialize)]
+struct GenerationDefinition {
+    name: String,
+    /// Key of the animal's texture in `dynamic.assets`
+    texture: String,
+    /// Key of the frames on the farm; without a sheet the texture is the only frame
+    #[serde(default)]
+    sprite_sheet: Option<String>,
+    #[serde(default)]
+    animations: AnimationClips,
+    /// Key of the sound played when this generation is bred
+    sound: String,
+    money_per_second: f64,
+    /// Walking speed in units per second
+    #[serde(default = "default_speed")]
+    speed: f32,
+    /// Tint applied to the texture, so hybrids can reuse the art of other generations
+    #[serde(default)]
+    color: Option<(f32, f32, f32)>,
+    /// Name of the generation two animals of this generation merge into
+    ///
+    /// This is a shorthand for a recipe with two identical parents.
+    #[serde(default)]
+    next: Option<String>,
+}
+
+fn default_speed() -> f32 {
+    60.
+}
+
+/// Two animals of the parent generations can be merged into the child generation
+#[derive(Deserialize)]
+struct RecipeDefinition {
+    parents: (String, String),
+    child: String,
+}
+
+/// Sprite sheet as declared in `dynamic.assets`
+struct SheetFile<'a> {
+    path: &'a str,
+    tile_size: Vec2,
+    columns: usize,
+    rows: usize,
+}
+
+/// Asset paths of the files a generation references
+struct GenerationFiles<'a> {
+    texture: &'a str,
+    sprite_sheet: Option<&'a str>,
+    sound: &'a str,
+}
+
+/// What the asset server hands out for the files a generation references
+#[derive(Default)]
+struct GenerationHandles {
+    texture: Handle<Image>,
+    sprite_sheet: Option<Handle<Image>>,
+    sound: Handle<AudioSource>,
+}
+
+/// Resolve names and keys and check the definitions for dangling references
+fn build_generations(
+    definition: GenerationsDefinition,
+    declared: &DeclaredAssets,
+    mut load: impl FnMut(GenerationFiles) -> GenerationHandles,
+) -> anyhow::Result<Generations> {
+    if definition.generations.is_empty() {
+        bail!("at least one animal generation has to be defined");
+    }
+    let mut indices = HashMap::default();
+    for (index, generation) in definition.generations.iter().enumerate() {
+        if indices
+            .insert(generation.name.clone(), AnimalGeneration(index))
+            .is_some()
+        {
+            bail!("animal generation '{}' is defined twice", generation.name);
+        }
+    }
+    let resolve = |name: &String, referenced_by: &str| match indices.get(name) {
+        Some(generation) => Ok(*generation),
+        None => Err(anyhow!(
+            "{} references unknown animal generation '{}'",
+            referenced_by,
+            name
+        )),
+    };
+
+    let mut recipes = vec![];
+    let mut generations = Vec::with_capacity(definition.generations.len());
+    for generation in definition.generations.iter() {
+        let referenced_by = format!("animal generation '{}'", generation.name);
+        if let Some(next) = &generation.next {
+            let parent = indices[&generation.name];
+            recipes.push(((parent, parent), resolve(next, &referenced_by)?));
+        }
+        let sheet = match &generation.sprite_sheet {
+            Some(key) => Some(declared.sprite_sheet(key, &referenced_by)?),
+            None => None,
+        };
+        let frames = sheet.as_ref().map_or(1, |sheet| sheet.columns * sheet.rows);
+        let clips = &generation.animations;
+        for clip in [&clips.idle, &clips.walk, &clips.picked, &clips.hatch] {
+            if clip.is_empty() {
+                bail!("{} has an animation without frames", referenced_by);
+            }
+            if let Some(frame) = clip.iter().find(|frame| **frame >= frames) {
+                bail!(
+                    "{} uses frame {} of a sprite sheet with {} frames",
+                    referenced_by,
+                    frame,
+                    frames
+                );
+            }
+        }
+        let handles = load(GenerationFiles {
+            texture: declared.file(&generation.texture, &referenced_by)?,
+            sprite_sheet: sheet.as_ref().map(|sheet| sheet.path),
+            sound: declared.file(&generation.sound, &referenced_by)?,
+        });
+        let sheet = match (sheet, handles.sprite_sheet) {
+            (Some(sheet), Some(texture)) => SpriteSheet {
+                texture,
+                tile_size: sheet.tile_size,
+                columns: sheet.columns,
+                rows: sheet.rows,
+            },
+            _ => SpriteSheet {
+                texture: handles.texture.clone(),
+                tile_size: Vec2::splat(ANIMAL_SIZE),
+                columns: 1,
+                rows: 1,
+            },
+        };
+        generations.push(Generation {
+            name: generation.name.clone(),
+            texture: handles.texture,
+            sheet,
+            atlas: Default::default(),
+            animations: generation.animations.clone(),
+            sound: handles.sound,
+            color: generation.color.map_or(Color::WHITE, |(red, green, blue)| {
+                Color::rgb(red, green, blue)
+            }),
+            money_per_second: Money::from(generation.money_per_second),
+            speed: generation.speed,
+        });
+    }
+    for recipe in definition.recipes.iter() {
+        let referenced_by = format!("recipe for '{}'", recipe.child);
+        recipes.push((
+            (
+                resolve(&recipe.parents.0, &referenced_by)?,
+                resolve(&recipe.parents.1, &referenced_by)?,
+            ),
+            resolve(&recipe.child, &referenced_by)?,
+        ));
+    }
+
+    let mut generations = Generations::new(generations);
+    for ((first, second), child) in recipes {
+        if let Some(existing) = generations.add_recipe(first, second, child) {
+            bail!(
+                "'{}' and '{}' are bred into both '{}' and '{}'",
+                generations[first].name,
+                generations[second].name,
+                generations[existing].name,
+                generations[child].name
+            );
+        }
+    }
+
+    Ok(generations)
+}
+
+#[derive(Default)]
+struct GenerationsLoader;
+
+impl AssetLoader for GenerationsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let definition: GenerationsDefinition = ron::de::from_bytes(bytes)?;
+            let declared: DeclaredAssets =
+                ron::de::from_bytes(&load_context.read_asset_bytes(DYNAMIC_ASSETS).await?)?;
+            let mut dependencies = vec![];
+            let generations = build_generations(definition, &declared, |files| {
+                let texture = AssetPath::new(PathBuf::from(files.texture), None);
+                let sound = AssetPath::new(PathBuf::from(files.sound), None);
+                let sprite_sheet = files
+                    .sprite_sheet
+                    .map(|path| AssetPath::new(PathBuf::from(path), None));
+                let handles = GenerationHandles {
+                    texture: load_context.get_handle(texture.get_id()),
+                    sprite_sheet: sprite_sheet
+                        .as_ref()
+                        .map(|sheet| load_context.get_handle(sheet.get_id())),
+                    sound: load_context.get_handle(sound.get_id()),
+                };
+                dependencies.push(texture);
+                dependencies.push(sound);
+                dependencies.extend(sprite_sheet);
+                handles
+            })?;
+            load_context
+                .set_default_asset(LoadedAsset::new(generations).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["generations.ron"]
+    }
+}
+
+pub fn read_upgrades(path: &Path) -> anyhow::Result<Upgrades> {
+    check_upgrades(ron::de::from_bytes(&fs::read(path)?)?)
+}
+
+/// Catch typos in ids and nonsensical cost curves when loading instead of in the shop
+fn check_upgrades(upgrades: Upgrades) -> anyhow::Result<Upgrades> {
+    for (id, upgrade) in upgrades.iter() {
+        if upgrades.by_id(&upgrade.id) != Some(id) {
+            bail!("upgrade '{}' is defined twice", upgrade.id);
+        }
+        if upgrade.cost.base < 0. || upgrade.cost.growth <= 0. {
+            bail!(
+                "upgrade '{}' needs a positive cost curve, got {:?}",
+                upgrade.id,
+                upgrade.cost
+            );
+        }
+        for (required, _) in upgrade.requires.iter() {
+            if upgrades.by_id(required).is_none() {
+                bail!(
+                    "upgrade '{}' requires unknown upgrade '{}'",
+                    upgrade.id,
+                    required
+                );
+            }
+        }
+    }
+
+    Ok(upgrades)
+}
+
+#[derive(Default)]
+struct UpgradesLoader;
+
+impl AssetLoader for UpgradesLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let upgrades = check_upgrades(ron::de::from_bytes(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(upgrades));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["upgrades.ron"]
+    }
+}
+
+/// Unlocked achievements are saved by id, so ids have to be unique
+fn check_achievements(achievements: Achievements) -> anyhow::Result<Achievements> {
+    for (id, achievement) in achievements.iter() {
+        if achievements.by_id(&achievement.id) != Some(id) {
+            bail!("achievement '{}' is defined twice", achievement.id);
+        }
+    }
+
+    Ok(achievements)
+}
+
+#[derive(Default)]
+struct AchievementsLoader;
+
+impl AssetLoader for AchievementsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let achievements = check_achievements(ron::de::from_bytes(bytes)?)?;
+            load_context.set_default_asset(LoadedAsset::new(achievements));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["achievements.ron"]
+    }
+}
+
+/// Content of a `*.layers.ron` file
+#[derive(Deserialize)]
+struct MusicLayersDefinition {
+    layers: Vec<MusicLayerDefinition>,
+}
+
+#[derive(Deserialize)]
+struct MusicLayerDefinition {
+    /// Key of the track in `dynamic.assets`
+    track: String,
+    /// Name of the animal generation that unlocks this layer
+    #[serde(default)]
+    after: Option<String>,
+}
+
+#[derive(Default)]
+struct MusicLayersLoader;
+
+impl AssetLoader for MusicLayersLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let definition: MusicLayersDefinition = ron::de::from_bytes(bytes)?;
+            if definition.layers.is_empty() {
+                bail!("at least one music layer has to be defined");
+            }
+            let declared: DeclaredAssets =
+                ron::de::from_bytes(&load_context.read_asset_bytes(DYNAMIC_ASSETS).await?)?;
+            let mut dependencies = vec![];
+            let mut layers = vec![];
+            for layer in definition.layers {
+                let track = AssetPath::new(
+                    PathBuf::from(declared.file(&layer.track, "music layer")?),
+                    None,
+                );
+                layers.push(MusicLayer {
+                    track: load_context.get_handle(track.get_id()),
+                    after: layer.after,
+                });
+                dependencies.push(track);
+            }
+            load_context.set_default_asset(
+                LoadedAsset::new(MusicLayers { layers }).with_dependencies(dependencies),
+            );
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["layers.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prestige::Prestige;
+    use crate::statistics::Statistics;
+
+    /// Definition of a generation whose files are declared in [`declared`]
+    fn generation(name: &str, next: Option<&str>) -> String {
+        format!(
+            r#"(name: "{}", texture: "texture", sound: "sound", money_per_second: 1., next: {:?})"#,
+            name, next
+        )
+    }
+
+    fn declared() -> DeclaredAssets {
+        ron::from_str(
+            r#"({
+                "texture": File(path: "a.png"),
+                "sheet": TextureAtlas(path: "b.png", tile_size_x: 64., tile_size_y: 64., columns: 2, rows: 1),
+                "sound": File(path: "a.ogg"),
+            })"#,
+        )
+        .unwrap()
+    }
+
+    fn parse(generations: &[String], recipes: &[&str]) -> anyhow::Result<Generations> {
+        let definition = ron::from_str(&format!(
+            "(generations: [{}], recipes: [{}])",
+            generations.join(", "),
+            recipes.join(", ")
+        ))?;
+
+        build_generations(definition, &declared(), |_| Default::default())
+    }
+
+    fn error(generations: &[String], recipes: &[&str]) -> String {
+        match parse(generations, recipes) {
+            Ok(_) => panic!("expected the generations to be rejected"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    /// Two hens breed a duck; the goose needs a recipe
+    fn hen_duck_and_goose() -> [String; 3] {
+        [
+            generation("Hen", Some("Duck")),
+            generation("Duck", None),
+            generation("Goose", None),
+        ]
+    }
+
+    #[test]
+    fn the_shipped_generations_are_valid() {
+        let generations = read_generations(Path::new("assets/animals.generations.ron")).unwrap();
+
+        assert_eq!(generations[generations.first()].name, "Chicken");
+    }
+
+    #[test]
+    fn the_shipped_achievements_unlock() {
+        let achievements = check_achievements(
+            ron::de::from_bytes(&fs::read("assets/farm.achievements.ron").unwrap()).unwrap(),
+        )
+        .unwrap();
+        let generations = read_generations(Path::new("assets/animals.generations.ron")).unwrap();
+        let statistics = Statistics {
+            eggs_hatched: 1,
+            merges: 1,
+            upgrades_bought: 1,
+            bred: [generations.by_name("GoldenChicken").unwrap()]
+                .into_iter()
+                .collect(),
+        };
+        let prestige = Prestige {
+            stars: 0,
+            lifetime_earnings: Money::from(1e3),
+        };
+
+        let unlocked: Vec<_> = achievements
+            .iter()
+            .filter(|(_, achievement)| {
+                let (current, target) =
+                    achievement
+                        .condition
+                        .progress(&statistics, &prestige, &generations);
+                current >= target
+            })
+            .map(|(_, achievement)| achievement.id.as_str())
+            .collect();
+        assert_eq!(
+            unlocked,
+            [
+                "first_hatch",
+                "first_merge",
+                "golden_chicken",
+                "earn_1k",
+                "first_upgrade"
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_next_generations() {
+        let generations = parse(
+            &[generation("Egg", Some("Hen")), generation("Hen", None)],
+            &[],
+        )
+        .unwrap();
+        let egg = generations.by_name("Egg").unwrap();
+        let hen = generations.by_name("Hen").unwrap();
+
+        assert_eq!(generations.first(), egg);
+        assert_eq!(generations.breed(egg, egg), Some(hen));
+        assert_eq!(generations.breed(hen, hen), None);
+        assert_eq!(generations[hen].speed, default_speed());
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let error = error(&[generation("Hen", None), generation("Hen", None)], &[]);
+
+        assert!(error.contains("'Hen' is defined twice"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_unknown_next_generations() {
+        let error = error(&[generation("Hen", Some("Rooster"))], &[]);
+
+        assert!(
+            error.contains("unknown animal generation 'Rooster'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_asset_keys() {
+        let error = error(
+            &[generation("Hen", None).replace("\"sound\"", "\"cluck\"")],
+            &[],
+        );
+
+        assert!(error.contains("unknown asset key 'cluck'"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_empty_generations() {
+        assert!(parse(&[], &[]).is_err());
+    }
+
+    #[test]
+    fn recipes_work_in_both_orders() {
+        let generations = parse(
+            &hen_duck_and_goose(),
+            &[r#"(parents: ("Hen", "Duck"), child: "Goose")"#],
+        )
+        .unwrap();
+        let hen = generations.by_name("Hen").unwrap();
+        let duck = generations.by_name("Duck").unwrap();
+        let goose = generations.by_name("Goose").unwrap();
+
+        assert_eq!(generations.breed(hen, duck), Some(goose));
+        assert_eq!(generations.breed(duck, hen), Some(goose));
+        assert_eq!(generations.breed(hen, hen), Some(duck));
+        assert_eq!(generations.breed(duck, goose), None);
+    }
+
+    #[test]
+    fn rejects_recipes_with_unknown_parents() {
+        let error = error(
+            &hen_duck_and_goose(),
+            &[r#"(parents: ("Hen", "Swan"), child: "Goose")"#],
+        );
+
+        assert!(error.contains("recipe for 'Goose'"), "{}", error);
+        assert!(
+            error.contains("unknown animal generation 'Swan'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_recipes_with_unknown_children() {
+        let error = error(
+            &hen_duck_and_goose(),
+            &[r#"(parents: ("Hen", "Duck"), child: "Swan")"#],
+        );
+
+        assert!(
+            error.contains("unknown animal generation 'Swan'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_recipes() {
+        // two hens already breed a duck through `next`
+        let error = error(
+            &hen_duck_and_goose(),
+            &[r#"(parents: ("Hen", "Hen"), child: "Goose")"#],
+        );
+
+        assert!(
+            error.contains("'Hen' and 'Hen' are bred into both"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_frames_outside_of_the_sprite_sheet() {
+        let hen = generation("Hen", None).replace(
+            "sound:",
+            r#"sprite_sheet: Some("sheet"), animations: (walk: [0, 2]), sound:"#,
+        );
+        let error = error(&[hen], &[]);
+
+        assert!(
+            error.contains("uses frame 2 of a sprite sheet with 2 frames"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_files_used_as_sprite_sheets() {
+        let hen =
+            generation("Hen", None).replace("sound:", r#"sprite_sheet: Some("texture"), sound:"#);
+        let error = error(&[hen], &[]);
+
+        assert!(
+            error.contains("expects a sprite sheet under key 'texture'"),
+            "{}",
+            error
+        );
+    }
 }
@@ -0,0 +1,299 @@
+use crate::animal::Generations;
+use crate::loading::{AudioAssets, DataAssets, DeclaredAssets, FontAssets, TextureAssets};
+use crate::music::MusicLayers;
+use crate::GameState;
+use bevy::asset::{HandleId, LoadState};
+use bevy::prelude::*;
+
+/// Key of the font in `dynamic.assets`; shared with `FontAssets`, which is not loaded yet
+const FONT_KEY: &str = "fira_sans";
+const PROGRESS_BAR_WIDTH: f32 = 300.;
+
+/// The asset collections of `LoadingPlugin`, one progress bar each
+///
+/// Textures and sounds referenced by the data files are added to the progress by their
+/// handle type once those files are loaded.
+const COLLECTIONS: [(&str, &[&str]); 4] = [
+    ("Fonts", FontAssets::KEYS),
+    ("Audio", AudioAssets::KEYS),
+    ("Textures", TextureAssets::KEYS),
+    ("Data", DataAssets::KEYS),
+];
+const AUDIO: usize = 1;
+const TEXTURES: usize = 2;
+
+/// Shows how far each asset collection got and names the asset that failed to load
+///
+/// Without this a missing file would leave the game on an empty screen forever.
+pub struct LoadingScreenPlugin;
+
+impl Plugin for LoadingScreenPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CollectionPaths>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Loading).with_system(spawn_loading_screen),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Loading).with_system(track_progress))
+            .add_system_set(
+                SystemSet::on_exit(GameState::Loading).with_system(despawn_loading_screen),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::LoadingFailed).with_system(spawn_error_screen),
+            );
+    }
+}
+
+/// Asset paths of every collection in [`COLLECTIONS`] and of the font
+///
+/// Resolved from the `dynamic.assets` the game was built with, since the loader only
+/// reads its copy once loading has begun.
+struct CollectionPaths {
+    font: String,
+    collections: Vec<Vec<String>>,
+}
+
+impl Default for CollectionPaths {
+    fn default() -> Self {
+        let declared: DeclaredAssets = ron::from_str(include_str!("../assets/dynamic.assets"))
+            .expect("dynamic.assets should be valid");
+        let path = |key: &str, referenced_by: &str| {
+            declared
+                .file(key, referenced_by)
+                .expect("Collections should only use declared keys")
+                .to_owned()
+        };
+        CollectionPaths {
+            font: path(FONT_KEY, "The loading screen"),
+            collections: COLLECTIONS
+                .iter()
+                .map(|(name, keys)| keys.iter().map(|key| path(key, name)).collect())
+                .collect(),
+        }
+    }
+}
+
+/// Path of the asset that failed to load
+struct LoadingError(String);
+
+#[derive(Component)]
+struct LoadingScreen;
+
+/// Filled part of the progress bar of the collection with this index in [`COLLECTIONS`]
+#[derive(Component)]
+struct ProgressBar(usize);
+
+#[derive(Component)]
+struct ProgressText(usize);
+
+fn spawn_loading_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    paths: Res<CollectionPaths>,
+) {
+    // the font collection is not loaded yet, but shares this handle
+    let font = asset_server.load(paths.font.as_str());
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::rgb(0.1, 0.1, 0.1)),
+            ..default()
+        })
+        .insert(LoadingScreen)
+        .with_children(|parent| {
+            for (index, (name, _)) in COLLECTIONS.iter().enumerate() {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            margin: Rect::all(Val::Px(5.)),
+                            ..default()
+                        },
+                        color: UiColor(Color::NONE),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(140.), Val::Auto),
+                                    ..default()
+                                },
+                                text: Text::with_section(
+                                    *name,
+                                    TextStyle {
+                                        font: font.clone(),
+                                        font_size: 20.0,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                    Default::default(),
+                                ),
+                                ..default()
+                            })
+                            .insert(ProgressText(index));
+                        parent
+                            .spawn_bundle(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(PROGRESS_BAR_WIDTH), Val::Px(16.)),
+                                    ..default()
+                                },
+                                color: UiColor(Color::rgb(0.25, 0.25, 0.25)),
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent
+                                    .spawn_bundle(NodeBundle {
+                                        style: Style {
+                                            size: Size::new(Val::Percent(0.), Val::Percent(100.)),
+                                            ..default()
+                                        },
+                                        color: UiColor(Color::rgb(0.9, 0.9, 0.9)),
+                                        ..default()
+                                    })
+                                    .insert(ProgressBar(index));
+                            });
+                    });
+            }
+        });
+}
+
+fn track_progress(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    paths: Res<CollectionPaths>,
+    generations: Res<Assets<Generations>>,
+    music_layers: Res<Assets<MusicLayers>>,
+    mut state: ResMut<State<GameState>>,
+    mut bars: Query<(&mut Style, &ProgressBar)>,
+    mut texts: Query<(&mut Text, &ProgressText)>,
+) {
+    let mut assets: Vec<Vec<HandleId>> = paths
+        .collections
+        .iter()
+        .map(|paths| {
+            paths
+                .iter()
+                .map(|path| HandleId::from(path.as_str()))
+                .collect()
+        })
+        .collect();
+    for (_, generations) in generations.iter() {
+        for (_, generation) in generations.iter() {
+            assets[TEXTURES].push(generation.texture.id);
+            assets[TEXTURES].push(generation.sheet.texture.id);
+            assets[AUDIO].push(generation.sound.id);
+        }
+    }
+    for (_, music_layers) in music_layers.iter() {
+        for layer in music_layers.layers.iter() {
+            assets[AUDIO].push(layer.track.id);
+        }
+    }
+
+    let mut progress = vec![];
+    for handles in assets.iter_mut() {
+        handles.sort_unstable();
+        handles.dedup();
+        let mut loaded = 0;
+        for handle in handles.iter() {
+            match asset_server.get_load_state(*handle) {
+                LoadState::Loaded => loaded += 1,
+                LoadState::Failed => {
+                    let path = asset_server.get_handle_path(*handle).map_or_else(
+                        || format!("{:?}", handle),
+                        |path| path.path().display().to_string(),
+                    );
+                    error!("Failed to load asset '{}'", path);
+                    commands.insert_resource(LoadingError(path));
+                    state.set(GameState::LoadingFailed).unwrap();
+                    return;
+                }
+                _ => (),
+            }
+        }
+        progress.push((loaded, handles.len()));
+    }
+
+    for (mut style, ProgressBar(index)) in bars.iter_mut() {
+        let (loaded, total) = progress[*index];
+        style.size.width = Val::Percent(100. * loaded as f32 / total as f32);
+    }
+    for (mut text, ProgressText(index)) in texts.iter_mut() {
+        let (loaded, total) = progress[*index];
+        text.sections[0].value = format!("{} {}/{}", COLLECTIONS[*index].0, loaded, total);
+    }
+}
+
+fn despawn_loading_screen(mut commands: Commands, screen: Query<Entity, With<LoadingScreen>>) {
+    for entity in screen.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_error_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    paths: Res<CollectionPaths>,
+    error: Res<LoadingError>,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load(paths.font.as_str()),
+        font_size: 25.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::rgb(0.1, 0.1, 0.1)),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    format!("Failed to load '{}'", error.0),
+                    text_style.clone(),
+                    Default::default(),
+                ),
+                ..default()
+            });
+            parent.spawn_bundle(TextBundle {
+                text: Text::with_section(
+                    "Please check that the file exists in the assets folder",
+                    TextStyle {
+                        font_size: 18.0,
+                        ..text_style
+                    },
+                    Default::default(),
+                ),
+                ..default()
+            });
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_collection_key_is_declared() {
+        let paths = CollectionPaths::default();
+
+        assert_eq!(paths.font, "fonts/FiraSans-Bold.ttf");
+        assert_eq!(paths.collections[AUDIO], ["music.layers.ron"]);
+        assert_eq!(paths.collections[TEXTURES].len(), TextureAssets::KEYS.len());
+    }
+}
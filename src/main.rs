@@ -3,11 +3,16 @@
 
 use bevy::prelude::{App, ClearColor, Color, Msaa, WindowDescriptor};
 use bevy::DefaultPlugins;
-use shmoo_farm::{GamePlugin, WINDOW_HEIGHT, WINDOW_WIDTH};
+use shmoo_farm::{GamePlugin, GameRng, WINDOW_HEIGHT, WINDOW_WIDTH};
+use std::env;
+use std::process;
 
 fn main() {
-    App::new()
-        .insert_resource(Msaa { samples: 1 })
+    let mut app = App::new();
+    if let Some(seed) = seed_from_args() {
+        app.insert_resource(GameRng::new(seed));
+    }
+    app.insert_resource(Msaa { samples: 1 })
         .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
         .insert_resource(WindowDescriptor {
             width: WINDOW_WIDTH,
@@ -19,3 +24,20 @@ fn main() {
         .add_plugin(GamePlugin)
         .run();
 }
+
+/// `--seed <number>` starts the game with a fixed random seed to reproduce a session
+fn seed_from_args() -> Option<u64> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            let seed = args.next().and_then(|seed| seed.parse().ok());
+            if seed.is_none() {
+                eprintln!("--seed expects an unsigned integer");
+                process::exit(2);
+            }
+            return seed;
+        }
+    }
+
+    None
+}
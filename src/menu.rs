@@ -0,0 +1,428 @@
+use crate::loading::FontAssets;
+use crate::save::{has_save_game, NewGame};
+use crate::settings::{write_settings, AudioSettings, Settings};
+use crate::ui::ButtonColors;
+use crate::GameState;
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+/// Main menu, pause menu and settings screen
+///
+/// Pausing pushes [`GameState::Paused`] on top of [`GameState::Playing`], so every system
+/// running `on_update(GameState::Playing)` stops until the game is resumed.
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(spawn_main_menu))
+            .add_system_set(SystemSet::on_resume(GameState::MainMenu).with_system(spawn_main_menu))
+            .add_system_set(SystemSet::on_pause(GameState::MainMenu).with_system(despawn_menu))
+            .add_system_set(SystemSet::on_exit(GameState::MainMenu).with_system(despawn_menu))
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(spawn_pause_menu))
+            .add_system_set(SystemSet::on_resume(GameState::Paused).with_system(spawn_pause_menu))
+            .add_system_set(SystemSet::on_pause(GameState::Paused).with_system(despawn_menu))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(despawn_menu))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Settings).with_system(spawn_settings_menu),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Settings)
+                    .with_system(despawn_menu)
+                    .with_system(save_settings),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(pause))
+            .add_system_set(SystemSet::on_update(GameState::Paused).with_system(back_on_escape))
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings)
+                    .with_system(back_on_escape)
+                    .with_system(drag_volume_sliders)
+                    .with_system(update_settings_menu),
+            )
+            .add_system(click_menu_buttons);
+    }
+}
+
+/// Root of the currently shown menu
+#[derive(Component)]
+struct Menu;
+
+#[derive(Component, Clone, Copy)]
+enum MenuButton {
+    Continue,
+    NewGame,
+    Resume,
+    Settings,
+    NumberFormat,
+    FloatingIncome,
+    Mute,
+    Back,
+    Quit,
+}
+
+/// Label of a button that shows the current value of a setting
+#[derive(Component)]
+struct ButtonText(MenuButton);
+
+#[derive(Clone, Copy)]
+enum Volume {
+    Master,
+    Music,
+    Effects,
+}
+
+impl Volume {
+    fn name(self) -> &'static str {
+        match self {
+            Volume::Master => "Master",
+            Volume::Music => "Music",
+            Volume::Effects => "Effects",
+        }
+    }
+
+    fn get(self, audio: &AudioSettings) -> f32 {
+        match self {
+            Volume::Master => audio.master,
+            Volume::Music => audio.music,
+            Volume::Effects => audio.effects,
+        }
+    }
+
+    fn get_mut(self, audio: &mut AudioSettings) -> &mut f32 {
+        match self {
+            Volume::Master => &mut audio.master,
+            Volume::Music => &mut audio.music,
+            Volume::Effects => &mut audio.effects,
+        }
+    }
+}
+
+#[derive(Component)]
+struct VolumeSlider(Volume);
+
+/// Filled part of a [`VolumeSlider`]
+#[derive(Component)]
+struct VolumeFill(Volume);
+
+#[derive(Component)]
+struct VolumeText(Volume);
+
+fn spawn_main_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+) {
+    let mut buttons = vec![];
+    if has_save_game() {
+        buttons.push((MenuButton::Continue, "Continue"));
+    }
+    buttons.push((MenuButton::NewGame, "New game"));
+    buttons.push((MenuButton::Settings, "Settings"));
+    buttons.push((MenuButton::Quit, "Quit"));
+    spawn_menu(
+        &mut commands,
+        &font_assets,
+        &button_colors,
+        "Shmoo Farm",
+        &[],
+        &buttons,
+    );
+}
+
+fn spawn_pause_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+) {
+    spawn_menu(
+        &mut commands,
+        &font_assets,
+        &button_colors,
+        "Paused",
+        &[],
+        &[
+            (MenuButton::Resume, "Resume"),
+            (MenuButton::Settings, "Settings"),
+            (MenuButton::Quit, "Save and quit"),
+        ],
+    );
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+) {
+    spawn_menu(
+        &mut commands,
+        &font_assets,
+        &button_colors,
+        "Settings",
+        &[Volume::Master, Volume::Music, Volume::Effects],
+        &[
+            (MenuButton::Mute, ""),
+            (MenuButton::NumberFormat, ""),
+            (MenuButton::FloatingIncome, ""),
+            (MenuButton::Back, "Back"),
+        ],
+    );
+}
+
+/// A dimmed full screen overlay with a title, volume sliders and a column of buttons
+fn spawn_menu(
+    commands: &mut Commands,
+    font_assets: &FontAssets,
+    button_colors: &ButtonColors,
+    title: &str,
+    sliders: &[Volume],
+    buttons: &[(MenuButton, &str)],
+) {
+    let text_style = TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size: 30.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::rgba(0.1, 0.1, 0.1, 0.9)),
+            ..default()
+        })
+        .insert(Menu)
+        .with_children(|parent| {
+            parent.spawn_bundle(TextBundle {
+                style: Style {
+                    margin: Rect {
+                        bottom: Val::Px(20.),
+                        ..default()
+                    },
+                    ..default()
+                },
+                text: Text::with_section(
+                    title,
+                    TextStyle {
+                        font_size: 50.0,
+                        ..text_style.clone()
+                    },
+                    Default::default(),
+                ),
+                ..default()
+            });
+            for volume in sliders {
+                parent
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Row,
+                            align_items: AlignItems::Center,
+                            margin: Rect::all(Val::Px(5.)),
+                            ..default()
+                        },
+                        color: UiColor(Color::NONE),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(160.), Val::Auto),
+                                    ..default()
+                                },
+                                text: Text::with_section(
+                                    volume.name(),
+                                    text_style.clone(),
+                                    Default::default(),
+                                ),
+                                ..default()
+                            })
+                            .insert(VolumeText(*volume));
+                        parent
+                            .spawn_bundle(ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(200.), Val::Px(20.)),
+                                    ..default()
+                                },
+                                color: button_colors.normal,
+                                ..default()
+                            })
+                            .insert(VolumeSlider(*volume))
+                            .with_children(|parent| {
+                                parent
+                                    .spawn_bundle(NodeBundle {
+                                        style: Style {
+                                            size: Size::new(Val::Percent(0.), Val::Percent(100.)),
+                                            ..default()
+                                        },
+                                        color: UiColor(Color::rgb(0.9, 0.9, 0.9)),
+                                        ..default()
+                                    })
+                                    .insert(VolumeFill(*volume));
+                            });
+                    });
+            }
+            for (button, label) in buttons {
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(260.0), Val::Px(50.0)),
+                            margin: Rect::all(Val::Px(5.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        color: button_colors.normal,
+                        ..default()
+                    })
+                    .insert(*button)
+                    .with_children(|parent| {
+                        parent
+                            .spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    *label,
+                                    text_style.clone(),
+                                    Default::default(),
+                                ),
+                                ..default()
+                            })
+                            .insert(ButtonText(*button));
+                    });
+            }
+        });
+}
+
+fn despawn_menu(mut commands: Commands, menus: Query<Entity, With<Menu>>) {
+    for menu in menus.iter() {
+        commands.entity(menu).despawn_recursive();
+    }
+}
+
+fn update_settings_menu(
+    settings: Res<Settings>,
+    added: Query<(), Added<Menu>>,
+    mut button_texts: Query<(&mut Text, &ButtonText), Without<VolumeText>>,
+    mut volume_texts: Query<(&mut Text, &VolumeText), Without<ButtonText>>,
+    mut fills: Query<(&mut Style, &VolumeFill)>,
+) {
+    if !settings.is_changed() && added.is_empty() {
+        return;
+    }
+    for (mut text, ButtonText(button)) in button_texts.iter_mut() {
+        match button {
+            MenuButton::NumberFormat => {
+                text.sections[0].value = format!("Numbers: {:?}", settings.number_format);
+            }
+            MenuButton::FloatingIncome => {
+                text.sections[0].value = if settings.floating_income {
+                    "Income numbers: on".to_string()
+                } else {
+                    "Income numbers: off".to_string()
+                };
+            }
+            MenuButton::Mute => {
+                text.sections[0].value = if settings.audio.muted {
+                    "Sound: off (M)".to_string()
+                } else {
+                    "Sound: on (M)".to_string()
+                };
+            }
+            _ => (),
+        }
+    }
+    for (mut text, VolumeText(volume)) in volume_texts.iter_mut() {
+        text.sections[0].value = format!(
+            "{} {:.0}%",
+            volume.name(),
+            volume.get(&settings.audio) * 100.
+        );
+    }
+    for (mut style, VolumeFill(volume)) in fills.iter_mut() {
+        style.size.width = Val::Percent(volume.get(&settings.audio) * 100.);
+    }
+}
+
+/// Set the volume from the cursor position while the mouse button is held on a slider
+fn drag_volume_sliders(
+    windows: Res<Windows>,
+    sliders: Query<(&Interaction, &Node, &GlobalTransform, &VolumeSlider)>,
+    mut settings: ResMut<Settings>,
+) {
+    let cursor = match windows
+        .get_primary()
+        .and_then(|window| window.cursor_position())
+    {
+        Some(cursor) => cursor,
+        None => return,
+    };
+    for (interaction, node, transform, VolumeSlider(volume)) in sliders.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        let left = transform.translation.x - node.size.x / 2.;
+        let value = ((cursor.x - left) / node.size.x).clamp(0., 1.);
+        // holding the mouse still should not count as a change
+        if volume.get(&settings.audio) != value {
+            *volume.get_mut(&mut settings.audio) = value;
+        }
+    }
+}
+
+fn save_settings(settings: Res<Settings>) {
+    write_settings(&settings);
+}
+
+fn pause(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        // otherwise the pause menu sees the same key press and closes right away
+        keys.reset(KeyCode::Escape);
+        state.push(GameState::Paused).unwrap();
+    }
+}
+
+fn back_on_escape(mut keys: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+    if keys.just_pressed(KeyCode::Escape) {
+        keys.reset(KeyCode::Escape);
+        state.pop().unwrap();
+    }
+}
+
+fn click_menu_buttons(
+    button_colors: Res<ButtonColors>,
+    mut state: ResMut<State<GameState>>,
+    mut settings: ResMut<Settings>,
+    mut new_game: ResMut<NewGame>,
+    mut exit: EventWriter<AppExit>,
+    mut interaction_query: Query<(&Interaction, &mut UiColor, &MenuButton), Changed<Interaction>>,
+) {
+    for (interaction, mut color, button) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => match button {
+                MenuButton::Continue => state.set(GameState::Playing).unwrap(),
+                MenuButton::NewGame => {
+                    new_game.0 = true;
+                    state.set(GameState::Playing).unwrap();
+                }
+                MenuButton::Resume | MenuButton::Back => state.pop().unwrap(),
+                MenuButton::Settings => state.push(GameState::Settings).unwrap(),
+                MenuButton::NumberFormat => {
+                    settings.number_format = settings.number_format.next();
+                }
+                MenuButton::FloatingIncome => {
+                    settings.floating_income = !settings.floating_income;
+                }
+                MenuButton::Mute => settings.audio.muted = !settings.audio.muted,
+                MenuButton::Quit => exit.send(AppExit),
+            },
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+            }
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use bevy_inspector_egui::{egui, Context, Inspectable};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Summands that are more than this many orders of magnitude smaller than the other one vanish
+const PRECISION: i64 = 17;
+
+/// An amount of gold stored as `mantissa * 10^exponent`
+///
+/// Late game prices and incomes grow far beyond the range and precision of `f32`.
+/// The mantissa is always normalized to `1 <= |mantissa| < 10` (or exactly zero).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Money {
+    mantissa: f64,
+    exponent: i64,
+}
+
+impl Money {
+    pub const ZERO: Money = Money {
+        mantissa: 0.,
+        exponent: 0,
+    };
+
+    pub fn new(mantissa: f64, exponent: i64) -> Self {
+        if mantissa == 0. || !mantissa.is_finite() {
+            return Money::ZERO;
+        }
+        let shift = mantissa.abs().log10().floor() as i64;
+        let mut mantissa = mantissa / 10f64.powi(shift as i32);
+        let mut exponent = exponent + shift;
+        // correct rounding errors of the logarithm
+        if mantissa.abs() >= 10. {
+            mantissa /= 10.;
+            exponent += 1;
+        } else if mantissa.abs() < 1. {
+            mantissa *= 10.;
+            exponent -= 1;
+        }
+
+        Money { mantissa, exponent }
+    }
+
+    pub fn mantissa(&self) -> f64 {
+        self.mantissa
+    }
+
+    pub fn exponent(&self) -> i64 {
+        self.exponent
+    }
+
+    /// Lossy conversion; amounts beyond the range of `f64` become infinite
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa * 10f64.powi(self.exponent.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+    }
+
+    /// Base 10 logarithm of the amount; only meaningful for positive amounts
+    pub fn log10(&self) -> f64 {
+        self.mantissa.log10() + self.exponent as f64
+    }
+
+    pub fn powi(&self, mut exponent: u32) -> Money {
+        let mut base = *self;
+        let mut result = Money::from(1.);
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent /= 2;
+        }
+
+        result
+    }
+
+    pub fn floor(&self) -> Money {
+        if self.exponent >= PRECISION {
+            return *self;
+        }
+
+        Money::from(self.to_f64().floor())
+    }
+}
+
+impl From<f64> for Money {
+    fn from(value: f64) -> Self {
+        Money::new(value, 0)
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+
+    fn add(self, other: Money) -> Money {
+        if other.mantissa == 0. {
+            return self;
+        }
+        if self.mantissa == 0. {
+            return other;
+        }
+        let (larger, smaller) = if self.exponent >= other.exponent {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let difference = larger.exponent - smaller.exponent;
+        if difference > PRECISION {
+            return larger;
+        }
+
+        Money::new(
+            larger.mantissa + smaller.mantissa / 10f64.powi(difference as i32),
+            larger.exponent,
+        )
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, other: Money) {
+        *self = *self + other;
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+
+    fn neg(self) -> Money {
+        Money {
+            mantissa: -self.mantissa,
+            exponent: self.exponent,
+        }
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+
+    fn sub(self, other: Money) -> Money {
+        self + -other
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, other: Money) {
+        *self = *self - other;
+    }
+}
+
+impl Mul for Money {
+    type Output = Money;
+
+    fn mul(self, other: Money) -> Money {
+        Money::new(
+            self.mantissa * other.mantissa,
+            self.exponent + other.exponent,
+        )
+    }
+}
+
+impl Mul<f64> for Money {
+    type Output = Money;
+
+    fn mul(self, factor: f64) -> Money {
+        self * Money::from(factor)
+    }
+}
+
+impl MulAssign<f64> for Money {
+    fn mul_assign(&mut self, factor: f64) {
+        *self = *self * factor;
+    }
+}
+
+impl Div for Money {
+    type Output = Money;
+
+    fn div(self, other: Money) -> Money {
+        Money::new(
+            self.mantissa / other.mantissa,
+            self.exponent - other.exponent,
+        )
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        iter.fold(Money::ZERO, Add::add)
+    }
+}
+
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Money) -> Option<Ordering> {
+        let sign = |money: &Money| money.mantissa.partial_cmp(&0.);
+        match sign(self)?.cmp(&sign(other)?) {
+            Ordering::Equal => (),
+            ordering => return Some(ordering),
+        }
+        if self.mantissa == 0. {
+            return Some(Ordering::Equal);
+        }
+        let by_exponent = if self.mantissa > 0. {
+            self.exponent.cmp(&other.exponent)
+        } else {
+            other.exponent.cmp(&self.exponent)
+        };
+        match by_exponent {
+            Ordering::Equal => self.mantissa.partial_cmp(&other.mantissa),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.exponent < 6 {
+            write!(f, "{:.0}", self.to_f64())
+        } else {
+            write!(f, "{:.2}e{}", self.mantissa, self.exponent)
+        }
+    }
+}
+
+impl Inspectable for Money {
+    type Attributes = ();
+
+    fn ui(&mut self, ui: &mut egui::Ui, _: Self::Attributes, context: &mut Context) -> bool {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            changed |= self.mantissa.ui(ui, Default::default(), context);
+            ui.label("e");
+            changed |= self.exponent.ui(ui, Default::default(), context);
+        });
+        if changed {
+            *self = Money::new(self.mantissa, self.exponent);
+        }
+
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Money, expected: Money) {
+        assert_eq!(
+            actual.exponent, expected.exponent,
+            "{:?} != {:?}",
+            actual, expected
+        );
+        assert!(
+            (actual.mantissa - expected.mantissa).abs() < 1e-9,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn normalizes_the_mantissa() {
+        assert_close(
+            Money::new(1234., 0),
+            Money {
+                mantissa: 1.234,
+                exponent: 3,
+            },
+        );
+        assert_close(
+            Money::new(0.05, 2),
+            Money {
+                mantissa: 5.,
+                exponent: 0,
+            },
+        );
+        assert_close(
+            Money::new(-25., 10),
+            Money {
+                mantissa: -2.5,
+                exponent: 11,
+            },
+        );
+        assert_eq!(Money::new(0., 5), Money::ZERO);
+        assert_eq!(Money::new(f64::NAN, 0), Money::ZERO);
+        assert_eq!(Money::new(f64::INFINITY, 0), Money::ZERO);
+    }
+
+    #[test]
+    fn adds_mixed_exponents() {
+        assert_close(Money::new(1., 3) + Money::new(5., 2), Money::new(1.5, 3));
+        assert_close(Money::new(5., 2) + Money::new(1., 3), Money::new(1.5, 3));
+        assert_close(Money::new(9., 0) + Money::new(2., 0), Money::new(1.1, 1));
+        assert_close(Money::new(1., 40) + Money::ZERO, Money::new(1., 40));
+        assert_close(Money::ZERO + Money::new(1., 40), Money::new(1., 40));
+    }
+
+    #[test]
+    fn tiny_summands_vanish() {
+        let large = Money::new(1., PRECISION + 1);
+        assert_eq!(large + Money::from(1.), large);
+        assert_eq!(Money::from(1.) + large, large);
+    }
+
+    #[test]
+    fn subtracts_mixed_exponents() {
+        assert_close(Money::new(1., 3) - Money::new(5., 2), Money::new(5., 2));
+        assert_close(Money::new(5., 2) - Money::new(1., 3), Money::new(-5., 2));
+        assert_eq!(Money::new(3., 7) - Money::new(3., 7), Money::ZERO);
+    }
+
+    #[test]
+    fn multiplies_and_divides() {
+        assert_close(Money::new(5., 10) * Money::new(4., 20), Money::new(2., 31));
+        assert_close(Money::new(2., 31) / Money::new(4., 20), Money::new(5., 10));
+        assert_close(Money::new(3., 5) * 0.5, Money::new(1.5, 5));
+    }
+
+    #[test]
+    fn orders_across_signs_and_exponents() {
+        let ordered = [
+            Money::new(-1., 10),
+            Money::new(-9., 2),
+            Money::new(-1., 2),
+            Money::ZERO,
+            Money::new(1., -3),
+            Money::new(9., 2),
+            Money::new(1., 3),
+            Money::new(2., 3),
+            Money::new(1., 100),
+        ];
+        for pair in ordered.windows(2) {
+            assert!(
+                pair[0] < pair[1],
+                "{:?} should be less than {:?}",
+                pair[0],
+                pair[1]
+            );
+            assert!(
+                pair[1] > pair[0],
+                "{:?} should be greater than {:?}",
+                pair[1],
+                pair[0]
+            );
+        }
+        assert_eq!(
+            Money::new(4., 4).partial_cmp(&Money::new(4., 4)),
+            Some(Ordering::Equal)
+        );
+    }
+
+    #[test]
+    fn raises_to_integer_powers() {
+        assert_close(Money::from(2.).powi(10), Money::from(1024.));
+        assert_close(Money::new(1., 100).powi(3), Money::new(1., 300));
+        assert_close(Money::new(5., 200).powi(0), Money::from(1.));
+        assert_close(Money::from(1.5).powi(2), Money::from(2.25));
+    }
+
+    #[test]
+    fn floors_small_amounts_only() {
+        assert_close(Money::from(12.7).floor(), Money::from(12.));
+        assert_close(Money::from(0.4).floor(), Money::ZERO);
+        let huge = Money::new(1.23456, 30);
+        assert_eq!(huge.floor(), huge);
+    }
+
+    #[test]
+    fn converts_to_f64() {
+        assert_eq!(Money::new(1.5, 3).to_f64(), 1500.);
+        assert!((Money::new(2., 5).log10() - (2f64.log10() + 5.)).abs() < 1e-12);
+        assert_eq!(Money::new(1., 400).to_f64(), f64::INFINITY);
+    }
+}
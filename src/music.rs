@@ -0,0 +1,256 @@
+use crate::animal::Generations;
+use crate::settings::Settings;
+use crate::statistics::Statistics;
+use crate::GameState;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy_kira_audio::{AudioApp, AudioChannel, AudioSource};
+
+/// Seconds it takes to fade from one track to the next
+const CROSSFADE_SECONDS: f32 = 3.;
+/// Music volume in the menus relative to the farm
+const MENU_LEVEL: f32 = 0.4;
+
+/// Background music that changes with the player's progress
+///
+/// The tracks are declared in `music.layers.ron`. The last layer whose generation was
+/// already bred plays; whenever that changes, the old track fades out while the new one
+/// fades in on the other channel.
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_audio_channel::<Music<0>>()
+            .add_audio_channel::<Music<1>>()
+            .init_resource::<MusicMixer>()
+            .add_system_set(SystemSet::on_enter(GameState::MainMenu).with_system(check_layers))
+            .add_system(mix_music);
+    }
+}
+
+/// One of the two channels the crossfade alternates between
+struct Music<const INDEX: usize>;
+
+#[derive(Clone, TypeUuid)]
+#[uuid = "3e8a5d0f-7c21-4b9e-a6f4-0d2c8b17e935"]
+pub struct MusicLayers {
+    pub layers: Vec<MusicLayer>,
+}
+
+#[derive(Clone)]
+pub struct MusicLayer {
+    pub track: Handle<AudioSource>,
+    /// Name of the generation that has to be bred before this layer plays
+    pub after: Option<String>,
+}
+
+impl MusicLayers {
+    /// Index of the layer matching the player's progress
+    fn current(&self, statistics: &Statistics, generations: &Generations) -> Option<usize> {
+        self.layers.iter().rposition(|layer| match &layer.after {
+            None => true,
+            Some(name) => generations
+                .by_name(name)
+                .map_or(false, |generation| statistics.bred.contains(&generation)),
+        })
+    }
+}
+
+#[derive(Default)]
+struct MusicMixer {
+    channels: [MixerChannel; 2],
+    /// Channel playing the current layer
+    active: usize,
+    /// Eases towards [`MENU_LEVEL`] in the menus and towards 1 on the farm
+    menu_level: f32,
+    /// Volume last sent to each channel, so unchanged volumes are not sent every frame
+    sent_volume: [Option<f32>; 2],
+}
+
+#[derive(Default)]
+struct MixerChannel {
+    layer: Option<usize>,
+    level: f32,
+}
+
+#[derive(SystemParam)]
+struct MusicChannels<'w, 's> {
+    channel_0: Res<'w, AudioChannel<Music<0>>>,
+    channel_1: Res<'w, AudioChannel<Music<1>>>,
+    #[system_param(ignore)]
+    marker: std::marker::PhantomData<&'s ()>,
+}
+
+impl<'w, 's> MusicChannels<'w, 's> {
+    fn play_looped(&self, channel: usize, track: Handle<AudioSource>) {
+        match channel {
+            0 => {
+                self.channel_0.stop();
+                self.channel_0.play_looped(track);
+            }
+            _ => {
+                self.channel_1.stop();
+                self.channel_1.play_looped(track);
+            }
+        }
+    }
+
+    fn stop(&self, channel: usize) {
+        match channel {
+            0 => self.channel_0.stop(),
+            _ => self.channel_1.stop(),
+        }
+    }
+
+    fn set_volume(&self, channel: usize, volume: f32) {
+        match channel {
+            0 => self.channel_0.set_volume(volume),
+            _ => self.channel_1.set_volume(volume),
+        }
+    }
+}
+
+/// Layers are loaded before the generations, so unknown names can only be found here
+fn check_layers(layers: Res<MusicLayers>, generations: Res<Generations>) {
+    for layer in layers.layers.iter() {
+        if let Some(name) = &layer.after {
+            if generations.by_name(name).is_none() {
+                warn!(
+                    "Music layer after unknown animal generation '{}' will never play",
+                    name
+                );
+            }
+        }
+    }
+}
+
+fn mix_music(
+    mut mixer: ResMut<MusicMixer>,
+    layers: Option<Res<MusicLayers>>,
+    generations: Option<Res<Generations>>,
+    statistics: Res<Statistics>,
+    settings: Res<Settings>,
+    state: Res<State<GameState>>,
+    time: Res<Time>,
+    channels: MusicChannels,
+) {
+    let (layers, generations) = match (layers, generations) {
+        (Some(layers), Some(generations)) => (layers, generations),
+        _ => return,
+    };
+    let mixer = &mut *mixer;
+    let step = time.delta_seconds() / CROSSFADE_SECONDS;
+
+    let layer = layers.current(&statistics, &generations);
+    if layer != mixer.channels[mixer.active].layer {
+        mixer.active = 1 - mixer.active;
+        let active = &mut mixer.channels[mixer.active];
+        active.layer = layer;
+        active.level = 0.;
+        match layer {
+            Some(layer) => channels.play_looped(mixer.active, layers.layers[layer].track.clone()),
+            None => channels.stop(mixer.active),
+        }
+        mixer.sent_volume[mixer.active] = None;
+    }
+
+    let menu_target = if state.current() == &GameState::Playing {
+        1.
+    } else {
+        MENU_LEVEL
+    };
+    mixer.menu_level = approach(mixer.menu_level, menu_target, step);
+    for (index, channel) in mixer.channels.iter_mut().enumerate() {
+        let target = if index == mixer.active { 1. } else { 0. };
+        let faded_out = channel.level > 0. && target == 0. && channel.level <= step;
+        channel.level = approach(channel.level, target, step);
+        if faded_out {
+            channels.stop(index);
+            channel.layer = None;
+        }
+        let volume = channel.level * mixer.menu_level * settings.audio.music_volume();
+        if mixer.sent_volume[index] != Some(volume) {
+            channels.set_volume(index, volume);
+            mixer.sent_volume[index] = Some(volume);
+        }
+    }
+}
+
+fn approach(current: f32, target: f32, step: f32) -> f32 {
+    if current < target {
+        (current + step).min(target)
+    } else {
+        (current - step).max(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loading::read_generations;
+    use std::path::Path;
+
+    fn layers(after: &[Option<&str>]) -> MusicLayers {
+        MusicLayers {
+            layers: after
+                .iter()
+                .map(|after| MusicLayer {
+                    track: Handle::default(),
+                    after: after.map(str::to_string),
+                })
+                .collect(),
+        }
+    }
+
+    fn bred(generations: &Generations, names: &[&str]) -> Statistics {
+        Statistics {
+            bred: names
+                .iter()
+                .map(|name| generations.by_name(name).unwrap())
+                .collect(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn approach_moves_by_one_step_without_overshooting() {
+        assert_eq!(approach(0., 1., 0.25), 0.25);
+        assert_eq!(approach(1., 0.4, 0.25), 0.75);
+        assert_eq!(approach(0.9, 1., 0.25), 1.);
+        assert_eq!(approach(0.5, 0.4, 0.25), 0.4);
+        assert_eq!(approach(0.4, 0.4, 0.25), 0.4);
+    }
+
+    #[test]
+    fn plays_the_last_unlocked_layer() {
+        let generations = read_generations(Path::new("assets/animals.generations.ron")).unwrap();
+        let layers = layers(&[None, Some("ChickenDuck"), Some("ChickenDuckGoat")]);
+
+        assert_eq!(
+            layers.current(&bred(&generations, &[]), &generations),
+            Some(0)
+        );
+        assert_eq!(
+            layers.current(&bred(&generations, &["ChickenDuck"]), &generations),
+            Some(1)
+        );
+        // breeding them out of order still picks the last layer
+        assert_eq!(
+            layers.current(&bred(&generations, &["ChickenDuckGoat"]), &generations),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn is_silent_until_the_first_layer_unlocks() {
+        let generations = read_generations(Path::new("assets/animals.generations.ron")).unwrap();
+        let layers = layers(&[Some("ChickenDuck"), Some("NoSuchAnimal")]);
+
+        assert_eq!(layers.current(&bred(&generations, &[]), &generations), None);
+        assert_eq!(
+            layers.current(&bred(&generations, &["ChickenDuck"]), &generations),
+            Some(0)
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use crate::money::Money;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+
+const SUFFIXES: [&str; 11] = ["K", "M", "B", "T", "Qa", "Qi", "Sx", "Sp", "Oc", "No", "Dc"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Inspectable, Serialize, Deserialize)]
+pub enum NumberFormat {
+    /// 1.23K, 45.6M, 789B, ...
+    Suffix,
+    /// Like [`NumberFormat::Suffix`] up to trillions, then 1.23aa, 1.23ab, ...
+    Letters,
+    /// 1.23e15
+    Scientific,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat::Suffix
+    }
+}
+
+impl NumberFormat {
+    /// Cycles through all formats, for a settings button
+    pub fn next(self) -> Self {
+        match self {
+            NumberFormat::Suffix => NumberFormat::Letters,
+            NumberFormat::Letters => NumberFormat::Scientific,
+            NumberFormat::Scientific => NumberFormat::Suffix,
+        }
+    }
+
+    pub fn format(&self, money: Money) -> String {
+        if money.exponent() < 3 {
+            return format_small(money.to_f64());
+        }
+        let group = money.exponent() / 3;
+        let suffix = match self {
+            NumberFormat::Suffix => SUFFIXES.get(group as usize - 1).map(|s| s.to_string()),
+            NumberFormat::Letters => letters(group),
+            NumberFormat::Scientific => None,
+        };
+        match suffix {
+            Some(suffix) => {
+                let digits_before_point = money.exponent() % 3;
+                let scaled = money.mantissa() * 10f64.powi(digits_before_point as i32);
+                format!(
+                    "{:.*}{}",
+                    2 - digits_before_point as usize,
+                    truncate(scaled, 2 - digits_before_point as i32),
+                    suffix
+                )
+            }
+            None => format!("{:.2}e{}", truncate(money.mantissa(), 2), money.exponent()),
+        }
+    }
+}
+
+/// Two lower case letters per group of three digits after the named suffixes run out
+fn letters(group: i64) -> Option<String> {
+    // K, M, B and T
+    let named = 4;
+    if group <= named {
+        return Some(SUFFIXES[group as usize - 1].to_string());
+    }
+    let index = group - named - 1;
+    if index >= 26 * 26 {
+        return None;
+    }
+    let letter = |offset: i64| (b'a' + offset as u8) as char;
+
+    Some(format!("{}{}", letter(index / 26), letter(index % 26)))
+}
+
+/// Numbers below one thousand get at most one decimal, which is dropped if it would be zero
+fn format_small(value: f64) -> String {
+    let value = truncate(value, 1);
+    if value.fract() == 0. {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+/// Round towards zero, so 999,999 is shown as 999K instead of 1000K
+fn truncate(value: f64, decimals: i32) -> f64 {
+    let factor = 10f64.powi(decimals);
+
+    (value * factor).trunc() / factor
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format_all(money: Money) -> [String; 3] {
+        [
+            NumberFormat::Suffix.format(money),
+            NumberFormat::Letters.format(money),
+            NumberFormat::Scientific.format(money),
+        ]
+    }
+
+    #[test]
+    fn small_numbers_look_the_same_in_every_format() {
+        assert_eq!(format_all(Money::from(999.)), ["999", "999", "999"]);
+        assert_eq!(format_all(Money::from(12.5)), ["12.5", "12.5", "12.5"]);
+        assert_eq!(format_all(Money::from(12.)), ["12", "12", "12"]);
+        assert_eq!(format_all(Money::ZERO), ["0", "0", "0"]);
+    }
+
+    #[test]
+    fn thousands() {
+        assert_eq!(format_all(Money::new(1., 3)), ["1.00K", "1.00K", "1.00e3"]);
+        assert_eq!(format_all(Money::from(1234.)), ["1.23K", "1.23K", "1.23e3"]);
+        assert_eq!(
+            format_all(Money::from(999_999.)),
+            ["999K", "999K", "9.99e5"]
+        );
+    }
+
+    #[test]
+    fn letters_start_after_trillions() {
+        assert_eq!(
+            format_all(Money::new(1., 12)),
+            ["1.00T", "1.00T", "1.00e12"]
+        );
+        assert_eq!(
+            format_all(Money::new(1., 15)),
+            ["1.00Qi", "1.00aa", "1.00e15"]
+        );
+        assert_eq!(
+            format_all(Money::new(2.5, 16)),
+            ["25.0Qi", "25.0aa", "2.50e16"]
+        );
+        assert_eq!(
+            format_all(Money::new(1., 18)),
+            ["1.00Sx", "1.00ab", "1.00e18"]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_scientific_when_out_of_names() {
+        // Dc is the last named suffix
+        assert_eq!(NumberFormat::Suffix.format(Money::new(1., 33)), "1.00Dc");
+        assert_eq!(NumberFormat::Suffix.format(Money::new(1., 36)), "1.00e36");
+        assert_eq!(NumberFormat::Letters.format(Money::new(1., 2040)), "1.00zz");
+        assert_eq!(
+            NumberFormat::Letters.format(Money::new(1., 2043)),
+            "1.00e2043"
+        );
+    }
+
+    #[test]
+    fn next_cycles_through_every_format() {
+        let cycle: Vec<_> =
+            std::iter::successors(Some(NumberFormat::Suffix), |format| Some(format.next()))
+                .take(4)
+                .collect();
+
+        assert_eq!(
+            cycle,
+            [
+                NumberFormat::Suffix,
+                NumberFormat::Letters,
+                NumberFormat::Scientific,
+                NumberFormat::Suffix
+            ]
+        );
+    }
+
+    #[test]
+    fn truncates_instead_of_rounding() {
+        assert_eq!(NumberFormat::Suffix.format(Money::new(9.999, 5)), "999K");
+        assert_eq!(
+            NumberFormat::Scientific.format(Money::new(9.999, 20)),
+            "9.99e20"
+        );
+    }
+}
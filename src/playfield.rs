@@ -0,0 +1,87 @@
+use crate::random::GameRng;
+use crate::{ANIMAL_SIZE, WINDOW_HEIGHT, WINDOW_WIDTH};
+use bevy::prelude::*;
+use bevy::window::{WindowId, WindowResized};
+use rand::Rng;
+
+/// The part of the window left of the sidebar in which animals and eggs can be placed
+///
+/// The bounds are in world coordinates and already leave room for half an animal at every
+/// edge, so a position inside the playfield keeps the whole sprite visible.
+pub struct Playfield {
+    window_size: Vec2,
+    /// Measured from the layout of the user interface
+    sidebar_width: f32,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl Playfield {
+    pub fn new(window_width: f32, window_height: f32, sidebar_width: f32) -> Self {
+        let min = Vec2::new(
+            ANIMAL_SIZE / 2. - window_width / 2.,
+            ANIMAL_SIZE / 2. - window_height / 2.,
+        );
+        let max = Vec2::new(
+            window_width / 2. - ANIMAL_SIZE / 2. - sidebar_width,
+            window_height / 2. - ANIMAL_SIZE / 2.,
+        );
+
+        Playfield {
+            window_size: Vec2::new(window_width, window_height),
+            sidebar_width,
+            min,
+            // windows too small for a single animal collapse the playfield instead of inverting it
+            max: max.max(min),
+        }
+    }
+
+    pub fn window_size(&self) -> Vec2 {
+        self.window_size
+    }
+
+    pub fn sidebar_width(&self) -> f32 {
+        self.sidebar_width
+    }
+
+    pub fn set_sidebar_width(&mut self, sidebar_width: f32) {
+        *self = Playfield::new(self.window_size.x, self.window_size.y, sidebar_width);
+    }
+
+    pub fn contains(&self, position: Vec2) -> bool {
+        position.cmpge(self.min).all() && position.cmple(self.max).all()
+    }
+
+    pub fn clamp(&self, position: Vec2) -> Vec2 {
+        position.clamp(self.min, self.max)
+    }
+
+    pub fn random_position(&self, rng: &mut GameRng) -> Vec2 {
+        Vec2::new(
+            rng.gen_range(self.min.x..=self.max.x),
+            rng.gen_range(self.min.y..=self.max.y),
+        )
+    }
+}
+
+/// The default window without a sidebar, which is all the headless simulation ever gets
+impl Default for Playfield {
+    fn default() -> Self {
+        Playfield::new(WINDOW_WIDTH, WINDOW_HEIGHT, 0.)
+    }
+}
+
+pub fn fit_playfield_to_window(
+    mut resized: EventReader<WindowResized>,
+    windows: Res<Windows>,
+    mut playfield: ResMut<Playfield>,
+) {
+    let resized = resized.iter().any(|event| event.id == WindowId::primary());
+    if !resized && !playfield.is_added() {
+        return;
+    }
+    if let Some(window) = windows.get_primary() {
+        let sidebar_width = playfield.sidebar_width;
+        *playfield = Playfield::new(window.width(), window.height(), sidebar_width);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::animal::Animal;
+use crate::automation::AutomationTimer;
+use crate::economy::Score;
+use crate::events::MoneyEarned;
+use crate::farm::{CurrentEggs, Egg, SpawnEggTimer};
+use crate::money::Money;
+use crate::upgrades::UpgradeLevels;
+use crate::GameState;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+
+/// Lifetime earnings needed for the first star
+const FIRST_STAR_EARNINGS: f64 = 1e6;
+const INCOME_BONUS_PER_STAR: f64 = 0.1;
+const EGG_SPEED_BONUS_PER_STAR: f64 = 0.02;
+
+pub struct PrestigePlugin;
+
+impl Plugin for PrestigePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Prestige>()
+            .add_event::<Rebirth>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(track_lifetime_earnings)
+                    .with_system(rebirth),
+            );
+    }
+}
+
+/// Progress that survives a rebirth
+#[derive(Default, Inspectable)]
+pub struct Prestige {
+    /// Prestige currency; every star permanently boosts income and egg speed
+    pub stars: u64,
+    /// All money ever earned, including previous runs
+    pub lifetime_earnings: Money,
+}
+
+impl Prestige {
+    pub fn income_multiplier(&self) -> f64 {
+        Self::income_multiplier_for(self.stars)
+    }
+
+    pub fn income_multiplier_for(stars: u64) -> f64 {
+        1. + INCOME_BONUS_PER_STAR * stars as f64
+    }
+
+    /// Factor by which the egg timer runs faster
+    pub fn egg_speed_multiplier(&self) -> f64 {
+        Self::egg_speed_multiplier_for(self.stars)
+    }
+
+    pub fn egg_speed_multiplier_for(stars: u64) -> f64 {
+        1. + EGG_SPEED_BONUS_PER_STAR * stars as f64
+    }
+
+    /// Stars a rebirth would award right now
+    ///
+    /// The total number of stars grows with the square root of the lifetime earnings,
+    /// so each rebirth only pays out the stars that were not claimed before.
+    pub fn projected_reward(&self) -> u64 {
+        if self.lifetime_earnings < Money::from(FIRST_STAR_EARNINGS) {
+            return 0;
+        }
+        let total = (self.lifetime_earnings / Money::from(FIRST_STAR_EARNINGS))
+            .to_f64()
+            .sqrt()
+            .floor() as u64;
+
+        total.saturating_sub(self.stars)
+    }
+
+    /// Lifetime earnings at which the next rebirth awards one more star
+    pub fn earnings_for_next_star(&self) -> Money {
+        let next_total = self.stars + self.projected_reward() + 1;
+
+        Money::from(FIRST_STAR_EARNINGS) * (next_total as f64 * next_total as f64)
+    }
+}
+
+/// Sent to reset the farm in exchange for the projected stars
+///
+/// Ignored while the reward would be zero.
+pub struct Rebirth;
+
+/// Everything a rebirth resets
+#[derive(SystemParam)]
+struct Run<'w, 's> {
+    commands: Commands<'w, 's>,
+    score: ResMut<'w, Score>,
+    current_eggs: ResMut<'w, CurrentEggs>,
+    upgrade_levels: ResMut<'w, UpgradeLevels>,
+    spawn_egg_timer: ResMut<'w, SpawnEggTimer>,
+    automation_timer: ResMut<'w, AutomationTimer>,
+    placed: Query<'w, 's, Entity, Or<(With<Animal>, With<Egg>)>>,
+}
+
+impl<'w, 's> Run<'w, 's> {
+    fn reset(&mut self) {
+        *self.score = default();
+        *self.current_eggs = default();
+        *self.upgrade_levels = default();
+        *self.spawn_egg_timer = default();
+        *self.automation_timer = default();
+        for entity in self.placed.iter() {
+            self.commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn track_lifetime_earnings(mut earnings: EventReader<MoneyEarned>, mut prestige: ResMut<Prestige>) {
+    for MoneyEarned(earned) in earnings.iter() {
+        prestige.lifetime_earnings += *earned;
+    }
+}
+
+fn rebirth(mut events: EventReader<Rebirth>, mut prestige: ResMut<Prestige>, mut run: Run) {
+    if events.iter().last().is_none() {
+        return;
+    }
+    let reward = prestige.projected_reward();
+    if reward == 0 {
+        return;
+    }
+    prestige.stars += reward;
+    run.reset();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prestige(stars: u64, lifetime_earnings: f64) -> Prestige {
+        Prestige {
+            stars,
+            lifetime_earnings: Money::from(lifetime_earnings),
+        }
+    }
+
+    #[test]
+    fn no_stars_before_the_first_million() {
+        assert_eq!(prestige(0, 0.).projected_reward(), 0);
+        assert_eq!(prestige(0, 999_999.).projected_reward(), 0);
+        assert_eq!(prestige(0, 1e6).projected_reward(), 1);
+    }
+
+    #[test]
+    fn stars_grow_with_the_square_root_of_the_earnings() {
+        assert_eq!(prestige(0, 3.99e6).projected_reward(), 1);
+        assert_eq!(prestige(0, 4e6).projected_reward(), 2);
+        assert_eq!(prestige(0, 1e8).projected_reward(), 10);
+        assert_eq!(prestige(0, 1e12).projected_reward(), 1000);
+    }
+
+    #[test]
+    fn claimed_stars_are_not_awarded_again() {
+        assert_eq!(prestige(1, 4e6).projected_reward(), 1);
+        assert_eq!(prestige(2, 4e6).projected_reward(), 0);
+        assert_eq!(prestige(5, 4e6).projected_reward(), 0);
+    }
+
+    #[test]
+    fn next_star_needs_the_next_square() {
+        assert_eq!(prestige(0, 0.).earnings_for_next_star(), Money::from(1e6));
+        assert_eq!(prestige(0, 4e6).earnings_for_next_star(), Money::from(9e6));
+        assert_eq!(prestige(3, 4e6).earnings_for_next_star(), Money::from(16e6));
+    }
+
+    #[test]
+    fn stars_boost_income_and_egg_speed() {
+        assert_eq!(prestige(0, 0.).income_multiplier(), 1.);
+        assert!((prestige(5, 0.).income_multiplier() - 1.5).abs() < 1e-12);
+        assert!((prestige(5, 0.).egg_speed_multiplier() - 1.1).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,53 @@
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Error, RngCore, SeedableRng};
+
+/// The single source of randomness for the game logic
+///
+/// Every system that needs random numbers takes this resource, so a run can be reproduced
+/// by starting it with the same seed. Without an explicit seed a random one is picked.
+pub struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(rand::random())
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+pub fn log_seed(rng: Res<GameRng>) {
+    info!("Random seed: {}", rng.seed());
+}
@@ -0,0 +1,614 @@
+use crate::achievements::{Achievements, UnlockedAchievements};
+use crate::animal::{Animal, Generations};
+use crate::automation::DEFAULT_AUTOMATION_INTERVAL;
+use crate::clock::GameClock;
+use crate::economy::Score;
+use crate::farm::{
+    random_egg_translation, spawn_egg, CurrentEggTime, CurrentEggs, CurrentMaxEggs, Egg, ANIMAL_Z,
+};
+use crate::money::Money;
+use crate::playfield::Playfield;
+use crate::prestige::Prestige;
+use crate::random::GameRng;
+use crate::statistics::Statistics;
+use crate::ui::OfflineProgress;
+use crate::upgrades::{Stat, UpgradeLevels, Upgrades};
+use crate::GameState;
+use bevy::app::AppExit;
+use bevy::ecs::schedule::ShouldRun;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Bump this and migrate the old layout whenever [`SaveGame`] changes in an incompatible way
+///
+/// New fields with `#[serde(default)]` keep older saves readable and need no bump.
+const SAVE_VERSION: u32 = 4;
+const AUTOSAVE_SECONDS: f32 = 30.;
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutosaveTimer>()
+            .init_resource::<NewGame>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(load_game))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(autosave))
+            .add_system_to_stage(CoreStage::Last, save_on_exit.with_run_criteria(farm_exists));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SaveGame {
+    version: u32,
+    /// Unix timestamp in seconds; used to calculate the offline progress on the next start
+    #[serde(default)]
+    saved_at: u64,
+    /// Random seed of the session that wrote the save; helps to reproduce bug reports
+    #[serde(default)]
+    seed: u64,
+    score: Money,
+    /// Bought levels by upgrade id as defined in `shop.upgrades.ron`
+    upgrades: HashMap<String, u32>,
+    animals: Vec<SavedAnimal>,
+    eggs: Vec<(f32, f32)>,
+    #[serde(default)]
+    prestige_stars: u64,
+    #[serde(default)]
+    lifetime_earnings: Money,
+    #[serde(default)]
+    statistics: SavedStatistics,
+    /// Ids of the unlocked achievements as defined in `farm.achievements.ron`
+    #[serde(default)]
+    achievements: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct SavedStatistics {
+    eggs_hatched: u64,
+    merges: u64,
+    upgrades_bought: u64,
+    /// Generation names of every animal the player ever owned
+    bred: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedAnimal {
+    /// Name of the generation as defined in `animals.generations.ron`
+    generation: String,
+    position: (f32, f32),
+}
+
+/// Just enough of any save game to pick the layout to parse it with
+#[derive(Deserialize)]
+struct SaveVersion {
+    version: u32,
+}
+
+/// Layout of versions 1 to 3, from before the upgrades were defined in `shop.upgrades.ron`
+///
+/// Versions 1 and 2 counted gold in `f32` instead of [`Money`] and version 1 named generations
+/// by an enum variant instead of a string. The seed, prestige and automation were added to
+/// version 3 and are missing from older saves.
+#[derive(Deserialize)]
+struct LegacySaveGame<Generation, Score> {
+    #[serde(default)]
+    saved_at: u64,
+    #[serde(default)]
+    seed: u64,
+    score: Score,
+    current_max_eggs: u8,
+    current_egg_time: f32,
+    animals: Vec<LegacySavedAnimal<Generation>>,
+    eggs: Vec<(f32, f32)>,
+    #[serde(default)]
+    prestige_stars: u64,
+    #[serde(default)]
+    lifetime_earnings: Money,
+    #[serde(default)]
+    auto_hatch: bool,
+    #[serde(default)]
+    auto_merge: bool,
+    #[serde(default)]
+    automation_interval: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct LegacySavedAnimal<Generation> {
+    generation: Generation,
+    position: (f32, f32),
+}
+
+/// The generations as they were hard coded before `animals.generations.ron` kept their names
+#[derive(Deserialize, Debug)]
+enum LegacyGeneration {
+    Chicken,
+    ChickenDuck,
+    ChickenDuckGoat,
+    ChickenDuckGoatSheep,
+    ChickenDuckGoatSheepPig,
+    ChickenDuckGoatSheepPigCow,
+    ChickenDuckGoatSheepPigCowRabbit,
+}
+
+impl<Generation, Score> LegacySaveGame<Generation, Score> {
+    fn migrate(
+        self,
+        generation_name: impl Fn(Generation) -> String,
+        score: impl Fn(Score) -> Money,
+    ) -> SaveGame {
+        // the old shop changed the stats by a fixed step per purchase
+        let mut upgrades = HashMap::new();
+        upgrades.insert(
+            "max_eggs".to_string(),
+            self.current_max_eggs
+                .saturating_sub(CurrentMaxEggs::default().0) as u32,
+        );
+        upgrades.insert(
+            "faster_eggs".to_string(),
+            (CurrentEggTime::default().0 - self.current_egg_time)
+                .round()
+                .max(0.) as u32,
+        );
+        if self.auto_hatch {
+            upgrades.insert("auto_hatch".to_string(), 1);
+        }
+        if self.auto_merge {
+            upgrades.insert("auto_merge".to_string(), 1);
+        }
+        if let Some(interval) = self.automation_interval {
+            upgrades.insert(
+                "faster_automation".to_string(),
+                ((DEFAULT_AUTOMATION_INTERVAL - interval) / 0.5)
+                    .round()
+                    .max(0.) as u32,
+            );
+        }
+
+        SaveGame {
+            version: SAVE_VERSION,
+            saved_at: self.saved_at,
+            seed: self.seed,
+            score: score(self.score),
+            upgrades,
+            animals: self
+                .animals
+                .into_iter()
+                .map(|animal| SavedAnimal {
+                    generation: generation_name(animal.generation),
+                    position: animal.position,
+                })
+                .collect(),
+            eggs: self.eggs,
+            prestige_stars: self.prestige_stars,
+            lifetime_earnings: self.lifetime_earnings,
+            statistics: SavedStatistics::default(),
+            achievements: vec![],
+        }
+    }
+}
+
+/// Set by the main menu to start a fresh farm instead of loading the save game
+///
+/// The old save is only overwritten by the next autosave.
+#[derive(Default)]
+pub struct NewGame(pub bool);
+
+struct AutosaveTimer(Timer);
+
+impl Default for AutosaveTimer {
+    fn default() -> Self {
+        AutosaveTimer(Timer::from_seconds(AUTOSAVE_SECONDS, true))
+    }
+}
+
+#[derive(SystemParam)]
+struct FarmState<'w, 's> {
+    score: Res<'w, Score>,
+    upgrades: Res<'w, Upgrades>,
+    upgrade_levels: Res<'w, UpgradeLevels>,
+    generations: Res<'w, Generations>,
+    rng: Res<'w, GameRng>,
+    prestige: Res<'w, Prestige>,
+    statistics: Res<'w, Statistics>,
+    achievements: Res<'w, Achievements>,
+    unlocked_achievements: Res<'w, UnlockedAchievements>,
+    animals: Query<'w, 's, (&'static Animal, &'static Transform)>,
+    eggs: Query<'w, 's, &'static Transform, With<Egg>>,
+}
+
+impl<'w, 's> FarmState<'w, 's> {
+    fn to_save_game(&self) -> SaveGame {
+        SaveGame {
+            version: SAVE_VERSION,
+            saved_at: 0,
+            seed: self.rng.seed(),
+            score: self.score.0,
+            upgrades: self
+                .upgrade_levels
+                .0
+                .iter()
+                .map(|(upgrade, level)| (self.upgrades[*upgrade].id.clone(), *level))
+                .collect(),
+            animals: self
+                .animals
+                .iter()
+                .map(|(animal, transform)| SavedAnimal {
+                    generation: self.generations[animal.generation].name.clone(),
+                    position: (transform.translation.x, transform.translation.y),
+                })
+                .collect(),
+            eggs: self
+                .eggs
+                .iter()
+                .map(|transform| (transform.translation.x, transform.translation.y))
+                .collect(),
+            prestige_stars: self.prestige.stars,
+            lifetime_earnings: self.prestige.lifetime_earnings,
+            statistics: SavedStatistics {
+                eggs_hatched: self.statistics.eggs_hatched,
+                merges: self.statistics.merges,
+                upgrades_bought: self.statistics.upgrades_bought,
+                bred: self
+                    .statistics
+                    .bred
+                    .iter()
+                    .map(|generation| self.generations[*generation].name.clone())
+                    .collect(),
+            },
+            achievements: self
+                .unlocked_achievements
+                .0
+                .iter()
+                .map(|achievement| self.achievements[*achievement].id.clone())
+                .collect(),
+        }
+    }
+}
+
+/// Directory for the save game and the settings
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("shmoo_farm"))
+}
+
+fn save_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("save.ron"))
+}
+
+pub fn has_save_game() -> bool {
+    save_file().map_or(false, |path| path.exists())
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn write_save(mut save: SaveGame) {
+    let path = match save_file() {
+        Some(path) => path,
+        None => return,
+    };
+    save.saved_at = unix_timestamp();
+    let serialized = match ron::ser::to_string_pretty(&save, Default::default()) {
+        Ok(serialized) => serialized,
+        Err(error) => {
+            error!("Failed to serialize save game: {}", error);
+            return;
+        }
+    };
+    if let Some(dir) = path.parent() {
+        if let Err(error) = fs::create_dir_all(dir) {
+            error!("Failed to create save directory {:?}: {}", dir, error);
+            return;
+        }
+    }
+    // write to a temporary file first, so a crash while saving cannot corrupt the last save
+    let temporary_path = path.with_extension("ron.tmp");
+    if let Err(error) =
+        fs::write(&temporary_path, serialized).and_then(|_| fs::rename(&temporary_path, &path))
+    {
+        error!("Failed to write save game to {:?}: {}", path, error);
+    }
+}
+
+fn read_save() -> Option<SaveGame> {
+    let path = save_file()?;
+    let serialized = fs::read_to_string(&path).ok()?;
+    match parse_save(&serialized) {
+        Ok(save) => save,
+        Err(error) => {
+            error!("Failed to parse save game {:?}: {}", path, error);
+            None
+        }
+    }
+}
+
+fn money_from_f32(amount: f32) -> Money {
+    Money::from(amount as f64)
+}
+
+/// Parse a save game of any known version into the current layout
+fn parse_save(serialized: &str) -> Result<Option<SaveGame>, ron::Error> {
+    let SaveVersion { version } = ron::from_str(serialized)?;
+    let save = match version {
+        SAVE_VERSION => ron::from_str(serialized)?,
+        3 => ron::from_str::<LegacySaveGame<String, Money>>(serialized)?
+            .migrate(|name| name, |score| score),
+        2 => ron::from_str::<LegacySaveGame<String, f32>>(serialized)?
+            .migrate(|name| name, money_from_f32),
+        1 => ron::from_str::<LegacySaveGame<LegacyGeneration, f32>>(serialized)?
+            .migrate(|generation| format!("{:?}", generation), money_from_f32),
+        _ => {
+            warn!(
+                "Ignoring save game with unsupported version {} (expected {})",
+                version, SAVE_VERSION
+            );
+            return Ok(None);
+        }
+    };
+
+    Ok(Some(save))
+}
+
+fn autosave(mut timer: ResMut<AutosaveTimer>, time: Res<Time>, farm: FarmState) {
+    timer.0.tick(time.delta());
+    if timer.0.just_finished() {
+        write_save(farm.to_save_game());
+    }
+}
+
+/// The farm only exists once `Playing` was entered; it keeps existing below the pause menu
+fn farm_exists(state: Res<State<GameState>>) -> ShouldRun {
+    if state.current() == &GameState::Playing || state.inactives().contains(&GameState::Playing) {
+        ShouldRun::Yes
+    } else {
+        ShouldRun::No
+    }
+}
+
+fn save_on_exit(mut exit: EventReader<AppExit>, farm: FarmState) {
+    if exit.iter().next().is_none() {
+        return;
+    }
+    write_save(farm.to_save_game());
+}
+
+fn load_game(
+    mut commands: Commands,
+    generations: Res<Generations>,
+    clock: Res<GameClock>,
+    mut score: ResMut<Score>,
+    mut current_eggs: ResMut<CurrentEggs>,
+    upgrades: Res<Upgrades>,
+    mut upgrade_levels: ResMut<UpgradeLevels>,
+    mut prestige: ResMut<Prestige>,
+    mut statistics: ResMut<Statistics>,
+    achievements: Res<Achievements>,
+    mut unlocked_achievements: ResMut<UnlockedAchievements>,
+    playfield: Res<Playfield>,
+    mut rng: ResMut<GameRng>,
+    mut new_game: ResMut<NewGame>,
+) {
+    if new_game.0 {
+        new_game.0 = false;
+        return;
+    }
+    let save = match read_save() {
+        Some(save) => save,
+        None => return,
+    };
+    info!("Loading save game written with random seed {}", save.seed);
+    score.0 = save.score;
+    for (id, level) in save.upgrades {
+        match upgrades.by_id(&id) {
+            Some(upgrade) => {
+                upgrade_levels.0.insert(upgrade, level);
+            }
+            None => warn!("Dropping level {} of unknown upgrade '{}'", level, id),
+        }
+    }
+    prestige.stars = save.prestige_stars;
+    prestige.lifetime_earnings = save.lifetime_earnings;
+    statistics.eggs_hatched = save.statistics.eggs_hatched;
+    statistics.merges = save.statistics.merges;
+    statistics.upgrades_bought = save.statistics.upgrades_bought;
+    // generations that were removed from the game are silently forgotten
+    statistics.bred = save
+        .statistics
+        .bred
+        .iter()
+        .filter_map(|name| generations.by_name(name))
+        .collect();
+    for id in save.achievements {
+        match achievements.by_id(&id) {
+            Some(achievement) => {
+                unlocked_achievements.0.insert(achievement);
+            }
+            None => warn!("Dropping unknown unlocked achievement '{}'", id),
+        }
+    }
+
+    let mut money_per_second = Money::ZERO;
+    for animal in save.animals {
+        let generation = match generations.by_name(&animal.generation) {
+            Some(generation) => generation,
+            None => {
+                warn!(
+                    "Dropping saved animal of unknown generation '{}'",
+                    animal.generation
+                );
+                continue;
+            }
+        };
+        money_per_second += generations[generation].money_per_second;
+        // the save might come from a larger window
+        let position = playfield.clamp(Vec2::new(animal.position.0, animal.position.1));
+        commands
+            .spawn_bundle(
+                generations[generation]
+                    .sprite(Transform::from_translation(position.extend(ANIMAL_Z))),
+            )
+            .insert(Animal::new(generation, clock.seconds_since_startup()));
+    }
+    current_eggs.0 = save.eggs.len() as u8;
+    for (x, y) in save.eggs {
+        let position = playfield.clamp(Vec2::new(x, y));
+        spawn_egg(&mut commands, position.extend(ANIMAL_Z));
+    }
+
+    // saves from before offline progress was introduced have no timestamp
+    if save.saved_at == 0 {
+        return;
+    }
+    let seconds_away = unix_timestamp().saturating_sub(save.saved_at);
+    if seconds_away == 0 {
+        return;
+    }
+    let money = money_per_second * prestige.income_multiplier() * seconds_away as f64;
+    let egg_seconds = seconds_away as f64 * prestige.egg_speed_multiplier();
+    // the farm resources only follow the upgrade levels in the next frame
+    let egg_time = upgrades.stat(Stat::EggTime, &upgrade_levels);
+    let max_eggs = upgrades.stat(Stat::MaxEggs, &upgrade_levels) as u8;
+    let eggs = ((egg_seconds / egg_time) as u8).min(max_eggs.saturating_sub(current_eggs.0));
+    for _ in 0..eggs {
+        spawn_egg(&mut commands, random_egg_translation(&playfield, &mut rng));
+    }
+    current_eggs.0 += eggs;
+    score.0 += money;
+    prestige.lifetime_earnings += money;
+    commands.insert_resource(OfflineProgress {
+        seconds: seconds_away,
+        money,
+        eggs,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERSION_1: &str = r#"(
+        version: 1,
+        score: 12.5,
+        current_max_eggs: 3,
+        current_egg_time: 8.0,
+        max_egg_price: 10000.0,
+        egg_time_price: 500.0,
+        animals: [
+            (
+                generation: ChickenDuck,
+                position: (1.0, 2.0),
+            ),
+        ],
+        eggs: [(3.0, 4.0)],
+    )"#;
+
+    #[test]
+    fn migrates_enum_generations_of_version_1() {
+        let save = parse_save(VERSION_1).unwrap().unwrap();
+
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.saved_at, 0);
+        assert_eq!(save.score, Money::from(12.5));
+        assert_eq!(save.upgrades["max_eggs"], 2);
+        assert_eq!(save.upgrades["faster_eggs"], 2);
+        assert_eq!(save.animals[0].generation, "ChickenDuck");
+        assert_eq!(save.animals[0].position, (1., 2.));
+        assert_eq!(save.eggs, vec![(3., 4.)]);
+    }
+
+    #[test]
+    fn migrates_version_2() {
+        let serialized = VERSION_1
+            .replace("version: 1", "version: 2, saved_at: 42")
+            .replace("ChickenDuck", "\"MuddyGoat\"");
+        let save = parse_save(&serialized).unwrap().unwrap();
+
+        assert_eq!(save.saved_at, 42);
+        assert_eq!(save.score, Money::from(12.5));
+        assert_eq!(save.animals[0].generation, "MuddyGoat");
+    }
+
+    #[test]
+    fn migrates_the_automation_of_version_3() {
+        let serialized = r#"(
+            version: 3,
+            saved_at: 42,
+            seed: 7,
+            score: (mantissa: 2.5, exponent: 30),
+            current_max_eggs: 5,
+            current_egg_time: 7.0,
+            max_egg_price: (mantissa: 1.0, exponent: 8),
+            egg_time_price: (mantissa: 1.0, exponent: 4),
+            animals: [],
+            eggs: [],
+            prestige_stars: 3,
+            lifetime_earnings: (mantissa: 1.0, exponent: 40),
+            auto_hatch: true,
+            auto_merge: false,
+            automation_interval: Some(3.5),
+            automation_speed_price: Some((mantissa: 8.0, exponent: 3)),
+        )"#;
+        let save = parse_save(serialized).unwrap().unwrap();
+
+        assert_eq!(save.seed, 7);
+        assert_eq!(save.score, Money::new(2.5, 30));
+        assert_eq!(save.upgrades["max_eggs"], 4);
+        assert_eq!(save.upgrades["faster_eggs"], 3);
+        assert_eq!(save.upgrades["auto_hatch"], 1);
+        assert!(!save.upgrades.contains_key("auto_merge"));
+        assert_eq!(save.upgrades["faster_automation"], 3);
+        assert_eq!(save.prestige_stars, 3);
+        assert_eq!(save.lifetime_earnings, Money::new(1., 40));
+    }
+
+    #[test]
+    fn reads_the_current_version_back() {
+        let save = SaveGame {
+            version: SAVE_VERSION,
+            saved_at: 7,
+            seed: 3,
+            score: Money::new(2., 40),
+            upgrades: HashMap::from([("max_eggs".to_string(), 2), ("auto_hatch".to_string(), 1)]),
+            animals: vec![SavedAnimal {
+                generation: "Chicken".to_string(),
+                position: (5., 6.),
+            }],
+            eggs: vec![],
+            prestige_stars: 4,
+            lifetime_earnings: Money::new(3., 50),
+            statistics: SavedStatistics {
+                eggs_hatched: 12,
+                merges: 5,
+                upgrades_bought: 2,
+                bred: vec!["Chicken".to_string()],
+            },
+            achievements: vec!["first_hatch".to_string()],
+        };
+        let serialized = ron::ser::to_string_pretty(&save, Default::default()).unwrap();
+        let read = parse_save(&serialized).unwrap().unwrap();
+
+        assert_eq!(read.saved_at, save.saved_at);
+        assert_eq!(read.seed, save.seed);
+        assert_eq!(read.score, save.score);
+        assert_eq!(read.upgrades, save.upgrades);
+        assert_eq!(read.prestige_stars, save.prestige_stars);
+        assert_eq!(read.lifetime_earnings, save.lifetime_earnings);
+        assert_eq!(read.statistics.eggs_hatched, save.statistics.eggs_hatched);
+        assert_eq!(read.statistics.bred, save.statistics.bred);
+        assert_eq!(read.achievements, save.achievements);
+        assert_eq!(read.animals[0].generation, "Chicken");
+    }
+
+    #[test]
+    fn ignores_unknown_versions() {
+        assert!(parse_save("(version: 99)").unwrap().is_none());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::number_format::NumberFormat;
+use crate::save::data_dir;
+use bevy::prelude::*;
+use bevy_inspector_egui::Inspectable;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(read_settings().unwrap_or_default())
+            .add_system(toggle_mute);
+    }
+}
+
+/// Player preferences; unlike the save game they are kept when starting a new game
+#[derive(Inspectable, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub number_format: NumberFormat,
+    pub audio: AudioSettings,
+    /// Show the income of every animal rising above it; costly on huge farms
+    pub floating_income: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            number_format: NumberFormat::default(),
+            audio: AudioSettings::default(),
+            floating_income: true,
+        }
+    }
+}
+
+#[derive(Inspectable, Serialize, Deserialize, Clone, Copy)]
+#[serde(default)]
+pub struct AudioSettings {
+    #[inspectable(min = 0., max = 1.)]
+    pub master: f32,
+    #[inspectable(min = 0., max = 1.)]
+    pub music: f32,
+    #[inspectable(min = 0., max = 1.)]
+    pub effects: f32,
+    pub muted: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            master: 1.,
+            music: 0.2,
+            effects: 1.,
+            muted: false,
+        }
+    }
+}
+
+impl AudioSettings {
+    pub fn music_volume(&self) -> f32 {
+        self.effective(self.music)
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        self.effective(self.effects)
+    }
+
+    fn effective(&self, channel: f32) -> f32 {
+        if self.muted {
+            0.
+        } else {
+            self.master * channel
+        }
+    }
+}
+
+fn settings_file() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("settings.ron"))
+}
+
+fn read_settings() -> Option<Settings> {
+    let path = settings_file()?;
+    let serialized = fs::read_to_string(&path).ok()?;
+    match ron::from_str(&serialized) {
+        Ok(settings) => Some(settings),
+        Err(error) => {
+            warn!("Ignoring invalid settings {:?}: {}", path, error);
+            None
+        }
+    }
+}
+
+pub fn write_settings(settings: &Settings) {
+    let path = match settings_file() {
+        Some(path) => path,
+        None => return,
+    };
+    let serialized = match ron::ser::to_string_pretty(settings, Default::default()) {
+        Ok(serialized) => serialized,
+        Err(error) => {
+            error!("Failed to serialize settings: {}", error);
+            return;
+        }
+    };
+    if let Some(dir) = path.parent() {
+        if let Err(error) = fs::create_dir_all(dir) {
+            error!("Failed to create settings directory {:?}: {}", dir, error);
+            return;
+        }
+    }
+    if let Err(error) = fs::write(&path, serialized) {
+        error!("Failed to write settings to {:?}: {}", path, error);
+    }
+}
+
+/// `M` mutes and unmutes the game everywhere, including the menus
+fn toggle_mute(keys: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keys.just_pressed(KeyCode::M) {
+        settings.audio.muted = !settings.audio.muted;
+        write_settings(&settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_ron() {
+        let settings = Settings {
+            number_format: NumberFormat::Scientific,
+            audio: AudioSettings {
+                master: 0.5,
+                music: 0.25,
+                effects: 0.75,
+                muted: true,
+            },
+            floating_income: false,
+        };
+        let serialized = ron::ser::to_string_pretty(&settings, Default::default()).unwrap();
+        let read: Settings = ron::from_str(&serialized).unwrap();
+
+        assert_eq!(read.number_format, NumberFormat::Scientific);
+        assert_eq!(read.audio.master, 0.5);
+        assert_eq!(read.audio.music, 0.25);
+        assert_eq!(read.audio.effects, 0.75);
+        assert!(read.audio.muted);
+        assert!(!read.floating_income);
+    }
+
+    #[test]
+    fn missing_fields_keep_their_defaults() {
+        let read: Settings = ron::from_str("(audio: (muted: true))").unwrap();
+
+        assert_eq!(read.number_format, NumberFormat::default());
+        assert_eq!(read.audio.master, AudioSettings::default().master);
+        assert_eq!(read.audio.music, AudioSettings::default().music);
+        assert!(read.audio.muted);
+        assert!(read.floating_income);
+    }
+
+    #[test]
+    fn muting_silences_every_channel() {
+        let audio = AudioSettings {
+            master: 0.5,
+            muted: true,
+            ..default()
+        };
+        assert_eq!(audio.music_volume(), 0.);
+        assert_eq!(audio.effects_volume(), 0.);
+
+        let audio = AudioSettings {
+            muted: false,
+            ..audio
+        };
+        assert_eq!(audio.effects_volume(), 0.5);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::actions::Actions;
+use crate::animal::{Animal, Generations, Picked};
+use crate::clock::GameClock;
+use crate::economy::Score;
+use crate::events::UpgradePurchased;
+use crate::farm::{income, CurrentEggTime, CurrentEggs, CurrentMaxEggs, Egg};
+use crate::loading::{read_generations, read_upgrades};
+use crate::money::Money;
+use crate::prestige::Prestige;
+use crate::upgrades::{BuyUpgrade, UpgradeLevels, Upgrades};
+use crate::{GameState, ShmooLabels, SimulationPlugin};
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Plays the game with a scripted strategy and prints a CSV timeline to stdout
+///
+/// Meant to run under `MinimalPlugins`: there is no window, the player input is replaced by
+/// the strategy and the [`GameClock`] advances by a fixed step every frame instead of
+/// following real time.
+pub struct BalancingPlugin {
+    pub generations: PathBuf,
+    pub upgrades: PathBuf,
+    /// In-game time to simulate
+    pub duration: Duration,
+    /// In-game time per frame
+    pub step: Duration,
+    /// In-game time between two rows of the timeline
+    pub report_interval: Duration,
+}
+
+impl Default for BalancingPlugin {
+    fn default() -> Self {
+        BalancingPlugin {
+            generations: PathBuf::from("assets/animals.generations.ron"),
+            upgrades: PathBuf::from("assets/shop.upgrades.ron"),
+            duration: Duration::from_secs(60 * 60),
+            step: Duration::from_millis(100),
+            report_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl Plugin for BalancingPlugin {
+    fn build(&self, app: &mut App) {
+        let generations = read_generations(&self.generations).unwrap_or_else(|error| {
+            panic!(
+                "Failed to read animal generations from {:?}: {:?}",
+                self.generations, error
+            )
+        });
+        let upgrades = read_upgrades(&self.upgrades).unwrap_or_else(|error| {
+            panic!(
+                "Failed to read upgrades from {:?}: {:?}",
+                self.upgrades, error
+            )
+        });
+        app.add_state(GameState::Playing)
+            .insert_resource(generations)
+            .insert_resource(upgrades)
+            .init_resource::<Actions>()
+            .init_resource::<Strategy>()
+            .insert_resource(Timeline {
+                duration: self.duration.as_secs_f64(),
+                step: self.step,
+                report_interval: self.report_interval.as_secs_f64(),
+                next_report: 0.,
+                purchases: vec![],
+            })
+            .add_plugin(SimulationPlugin)
+            .add_startup_system(print_header)
+            .add_system_to_stage(CoreStage::First, step_clock)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(play.label(ShmooLabels::ProcessActions))
+                    .with_system(buy_upgrades),
+            )
+            .add_system_to_stage(CoreStage::Last, report);
+    }
+}
+
+struct Timeline {
+    duration: f64,
+    step: Duration,
+    report_interval: f64,
+    next_report: f64,
+    /// Ids of the upgrades bought since the last row
+    purchases: Vec<String>,
+}
+
+/// Animal the strategy picked up in the last frame and wants to drop on another one
+#[derive(Default)]
+struct Strategy {
+    drop_on: Option<Entity>,
+}
+
+fn step_clock(mut clock: ResMut<GameClock>, timeline: Res<Timeline>) {
+    clock.advance(timeline.step);
+}
+
+/// Hatch every egg, then merge the pair of animals with the most valuable child
+///
+/// Works through [`Actions`] like a player would: pick an animal up in one frame
+/// and drop it on its partner in the next.
+fn play(
+    mut actions: ResMut<Actions>,
+    mut strategy: ResMut<Strategy>,
+    generations: Res<Generations>,
+    eggs: Query<&Transform, With<Egg>>,
+    animals: Query<(Entity, &Transform, &Animal), Without<Picked>>,
+) {
+    actions.just_pressed = false;
+    actions.just_released = false;
+    actions.position = None;
+
+    if let Some(partner) = strategy.drop_on.take() {
+        actions.just_released = true;
+        if let Ok((_, transform, _)) = animals.get(partner) {
+            actions.position = Some(transform.translation.truncate());
+        }
+        return;
+    }
+    if let Some(egg) = eggs.iter().next() {
+        actions.just_pressed = true;
+        actions.position = Some(egg.translation.truncate());
+        return;
+    }
+
+    let animals: Vec<_> = animals.iter().collect();
+    let mut best_merge: Option<(Entity, Entity, Money)> = None;
+    for (index, (first, first_transform, first_animal)) in animals.iter().enumerate() {
+        for (second, _, second_animal) in animals.iter().skip(index + 1) {
+            if let Some(child) =
+                generations.breed(first_animal.generation, second_animal.generation)
+            {
+                let income = generations[child].money_per_second;
+                if best_merge.map_or(true, |(_, _, best)| income > best) {
+                    best_merge = Some((*first, *second, income));
+                    actions.position = Some(first_transform.translation.truncate());
+                }
+            }
+        }
+    }
+    if let Some((_, partner, _)) = best_merge {
+        actions.just_pressed = true;
+        strategy.drop_on = Some(partner);
+    }
+}
+
+/// Buy the cheapest upgrade that is affordable
+fn buy_upgrades(
+    score: Res<Score>,
+    upgrades: Res<Upgrades>,
+    levels: Res<UpgradeLevels>,
+    mut buy: EventWriter<BuyUpgrade>,
+) {
+    let cheapest = upgrades
+        .iter()
+        .filter_map(|(upgrade, _)| {
+            upgrades
+                .check(upgrade, &levels, score.0)
+                .ok()
+                .map(|price| (upgrade, price))
+        })
+        .min_by(|(_, first), (_, second)| first.partial_cmp(second).unwrap());
+    if let Some((upgrade, _)) = cheapest {
+        buy.send(BuyUpgrade(upgrade));
+    }
+}
+
+fn print_header(generations: Res<Generations>) {
+    let mut columns = vec![
+        "seconds".to_string(),
+        "score".to_string(),
+        "income_per_second".to_string(),
+        "eggs".to_string(),
+        "max_eggs".to_string(),
+        "egg_time".to_string(),
+    ];
+    columns.extend(
+        generations
+            .iter()
+            .map(|(_, generation)| generation.name.clone()),
+    );
+    columns.push("purchases".to_string());
+    println!("{}", columns.join(","));
+}
+
+fn report(
+    mut timeline: ResMut<Timeline>,
+    clock: Res<GameClock>,
+    score: Res<Score>,
+    generations: Res<Generations>,
+    animals: Query<&Animal>,
+    current_eggs: Res<CurrentEggs>,
+    current_max_eggs: Res<CurrentMaxEggs>,
+    current_egg_time: Res<CurrentEggTime>,
+    upgrades: Res<Upgrades>,
+    mut purchased: EventReader<UpgradePurchased>,
+    prestige: Res<Prestige>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for UpgradePurchased { upgrade, .. } in purchased.iter() {
+        timeline.purchases.push(upgrades[*upgrade].id.clone());
+    }
+    let seconds = clock.seconds_since_startup();
+    let finished = seconds >= timeline.duration;
+    if seconds < timeline.next_report && !finished {
+        return;
+    }
+    timeline.next_report += timeline.report_interval;
+
+    let mut animals_per_generation = vec![0usize; generations.iter().count()];
+    for animal in animals.iter() {
+        animals_per_generation[animal.generation.0] += 1;
+    }
+    let income = income(animals.iter(), &generations, &prestige);
+    let mut columns = vec![
+        format!("{:.0}", seconds),
+        score.0.to_string(),
+        income.to_string(),
+        current_eggs.0.to_string(),
+        current_max_eggs.0.to_string(),
+        current_egg_time.0.to_string(),
+    ];
+    columns.extend(animals_per_generation.iter().map(ToString::to_string));
+    columns.push(timeline.purchases.join(" "));
+    timeline.purchases.clear();
+    println!("{}", columns.join(","));
+
+    if finished {
+        exit.send(AppExit);
+    }
+}
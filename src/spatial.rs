@@ -0,0 +1,205 @@
+use crate::animal::Animal;
+use crate::farm::Egg;
+use crate::{GameState, ShmooLabels, ANIMAL_SIZE};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialIndex>().add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .label(ShmooLabels::IndexPlacements)
+                .with_system(index_placements),
+        );
+    }
+}
+
+/// Side length of a grid cell; most lookups reach at most half an animal and touch few cells
+const CELL_SIZE: f32 = ANIMAL_SIZE;
+
+/// Animals and eggs sorted into grid cells once per frame, before the input is handled
+///
+/// Rebuilt every frame, so entities spawned or despawned since then are missing or stale.
+/// Lookups take a filter to skip those and anything else the caller is not interested in.
+#[derive(Default)]
+pub struct SpatialIndex {
+    animals: SpatialGrid,
+    eggs: SpatialGrid,
+}
+
+impl SpatialIndex {
+    pub fn animals(&self) -> &SpatialGrid {
+        &self.animals
+    }
+
+    pub fn eggs(&self) -> &SpatialGrid {
+        &self.eggs
+    }
+}
+
+#[derive(Default)]
+pub struct SpatialGrid {
+    /// Emptied cells are kept to reuse their allocation in the next frame
+    cells: HashMap<(i32, i32), Vec<(Entity, Vec3)>>,
+}
+
+impl SpatialGrid {
+    fn cell(position: Vec2) -> (i32, i32) {
+        let cell = (position / CELL_SIZE).floor();
+
+        (cell.x as i32, cell.y as i32)
+    }
+
+    fn clear(&mut self) {
+        for entries in self.cells.values_mut() {
+            entries.clear();
+        }
+    }
+
+    fn insert(&mut self, entity: Entity, translation: Vec3) {
+        self.cells
+            .entry(Self::cell(translation.truncate()))
+            .or_default()
+            .push((entity, translation));
+    }
+
+    /// The accepted entity closest to the position; the topmost one wins a tie
+    pub fn nearest(
+        &self,
+        position: Vec2,
+        reach: f32,
+        accept: impl Fn(Entity) -> bool,
+    ) -> Option<Entity> {
+        let (min_x, min_y) = Self::cell(position - Vec2::splat(reach));
+        let (max_x, max_y) = Self::cell(position + Vec2::splat(reach));
+        let mut nearest: Option<(Entity, f32, f32)> = None;
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                let entries = match self.cells.get(&(x, y)) {
+                    Some(entries) => entries,
+                    None => continue,
+                };
+                for (entity, translation) in entries {
+                    let distance = translation.truncate().distance(position);
+                    if distance >= reach || !accept(*entity) {
+                        continue;
+                    }
+                    let closer = nearest.map_or(true, |(_, best_distance, best_z)| {
+                        distance < best_distance
+                            || (distance == best_distance && translation.z > best_z)
+                    });
+                    if closer {
+                        nearest = Some((*entity, distance, translation.z));
+                    }
+                }
+            }
+        }
+
+        nearest.map(|(entity, _, _)| entity)
+    }
+}
+
+fn index_placements(
+    mut index: ResMut<SpatialIndex>,
+    animals: Query<(Entity, &Transform), With<Animal>>,
+    eggs: Query<(Entity, &Transform), (With<Egg>, Without<Animal>)>,
+) {
+    index.animals.clear();
+    for (entity, transform) in animals.iter() {
+        index.animals.insert(entity, transform.translation);
+    }
+    index.eggs.clear();
+    for (entity, transform) in eggs.iter() {
+        index.eggs.insert(entity, transform.translation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(entries: &[(u32, Vec3)]) -> SpatialGrid {
+        let mut grid = SpatialGrid::default();
+        for (id, translation) in entries {
+            grid.insert(Entity::from_raw(*id), *translation);
+        }
+
+        grid
+    }
+
+    #[test]
+    fn finds_entities_in_neighbouring_cells() {
+        let grid = grid(&[(1, Vec3::new(CELL_SIZE - 1., 0., 0.))]);
+
+        assert_eq!(
+            grid.nearest(Vec2::new(CELL_SIZE + 1., 0.), 5., |_| true),
+            Some(Entity::from_raw(1))
+        );
+        assert_eq!(
+            grid.nearest(Vec2::new(CELL_SIZE - 1., -3.), 5., |_| true),
+            Some(Entity::from_raw(1))
+        );
+        assert_eq!(
+            grid.nearest(Vec2::new(CELL_SIZE + 5., 0.), 5., |_| true),
+            None
+        );
+    }
+
+    #[test]
+    fn finds_entities_across_the_origin() {
+        let grid = grid(&[(1, Vec3::new(-1., -1., 0.))]);
+
+        assert_eq!(
+            grid.nearest(Vec2::new(1., 1.), 5., |_| true),
+            Some(Entity::from_raw(1))
+        );
+    }
+
+    #[test]
+    fn prefers_the_nearest_over_the_first() {
+        let grid = grid(&[
+            (1, Vec3::new(10., 0., 0.)),
+            (2, Vec3::new(CELL_SIZE + 2., 0., 0.)),
+            (3, Vec3::new(CELL_SIZE - 3., 0., 0.)),
+        ]);
+
+        assert_eq!(
+            grid.nearest(Vec2::new(CELL_SIZE, 0.), CELL_SIZE, |_| true),
+            Some(Entity::from_raw(2))
+        );
+    }
+
+    #[test]
+    fn prefers_the_topmost_of_equally_near_entities() {
+        let grid = grid(&[
+            (1, Vec3::new(5., 5., 1.)),
+            (2, Vec3::new(5., 5., 3.)),
+            (3, Vec3::new(5., 5., 2.)),
+        ]);
+
+        assert_eq!(
+            grid.nearest(Vec2::new(5., 5.), 5., |_| true),
+            Some(Entity::from_raw(2))
+        );
+    }
+
+    #[test]
+    fn skips_rejected_entities() {
+        let grid = grid(&[(1, Vec3::new(0., 0., 0.)), (2, Vec3::new(3., 0., 0.))]);
+
+        assert_eq!(
+            grid.nearest(Vec2::ZERO, 5., |entity| entity != Entity::from_raw(1)),
+            Some(Entity::from_raw(2))
+        );
+    }
+
+    #[test]
+    fn forgets_everything_when_cleared() {
+        let mut grid = grid(&[(1, Vec3::new(0., 0., 0.))]);
+        grid.clear();
+
+        assert_eq!(grid.nearest(Vec2::ZERO, 5., |_| true), None);
+    }
+}
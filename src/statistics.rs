@@ -0,0 +1,43 @@
+use crate::animal::AnimalGeneration;
+use crate::events::{AnimalsMerged, EggHatched, UpgradePurchased};
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+pub struct StatisticsPlugin;
+
+impl Plugin for StatisticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Statistics>()
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(count_events));
+    }
+}
+
+/// Lifetime counters of the player's progress; unlike the farm they survive a rebirth
+#[derive(Default)]
+pub struct Statistics {
+    pub eggs_hatched: u64,
+    pub merges: u64,
+    pub upgrades_bought: u64,
+    /// Every generation the player ever owned
+    pub bred: HashSet<AnimalGeneration>,
+}
+
+fn count_events(
+    mut statistics: ResMut<Statistics>,
+    mut hatched: EventReader<EggHatched>,
+    mut merged: EventReader<AnimalsMerged>,
+    mut purchased: EventReader<UpgradePurchased>,
+) {
+    for EggHatched { generation, .. } in hatched.iter() {
+        statistics.eggs_hatched += 1;
+        statistics.bred.insert(*generation);
+    }
+    for AnimalsMerged { to, .. } in merged.iter() {
+        statistics.merges += 1;
+        statistics.bred.insert(*to);
+    }
+    for _ in purchased.iter() {
+        statistics.upgrades_bought += 1;
+    }
+}
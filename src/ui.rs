@@ -1,62 +1,124 @@
-use crate::animal::{Animal, AnimalGeneration};
-use crate::farm::{CurrentEggTime, CurrentEggs, CurrentMaxEggs};
+use crate::achievements::{AchievementUnlocked, Achievements, Condition, UnlockedAchievements};
+use crate::animal::{Animal, AnimalGeneration, Generations};
+use crate::automation::Automation;
+use crate::economy::Score;
+use crate::farm::{income, CurrentEggTime, CurrentEggs, CurrentMaxEggs};
 use crate::loading::{FontAssets, TextureAssets};
+use crate::money::Money;
+use crate::playfield::Playfield;
+use crate::prestige::{Prestige, Rebirth};
+use crate::settings::Settings;
+use crate::statistics::Statistics;
+use crate::upgrades::{BuyUpgrade, Unavailable, UpgradeId, UpgradeLevels, Upgrades};
 use crate::GameState;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
-use bevy_inspector_egui::Inspectable;
-use strum::IntoEnumIterator;
 
-const UI_WIDTH: f32 = 180.;
+/// Width of the shop and collection on the right side of the window
+///
+/// The playfield measures the laid out sidebar instead of using this.
+const SIDEBAR_WIDTH: f32 = 180.;
+const UPGRADE_LIST_HEIGHT: f32 = 110.;
+const UPGRADE_ROW_HEIGHT: f32 = 22.;
+const TOAST_SECONDS: f32 = 4.;
 
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ButtonColors>()
-            .init_resource::<Score>()
-            .insert_resource(MaxEggPrice(100.))
-            .insert_resource(EggTimePrice(20.))
+            .add_startup_system(spawn_ui_camera)
             .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(spawn_ui))
+            .add_system(fit_playfield_to_sidebar)
             .add_system_set(
                 SystemSet::on_update(GameState::Playing)
                     .with_system(update_score)
+                    .with_system(update_income)
                     .with_system(update_current_eggs)
                     .with_system(update_current_max_eggs)
                     .with_system(update_question_marks)
-                    .with_system(buy_max_egg)
-                    .with_system(buy_faster_eggs)
-                    .with_system(update_egg_time),
+                    .with_system(update_egg_time)
+                    .with_system(show_offline_progress)
+                    .with_system(close_offline_progress)
+                    .with_system(update_automation)
+                    .with_system(update_upgrade_list)
+                    .with_system(buy_upgrades)
+                    .with_system(scroll_upgrade_list)
+                    .with_system(update_stars)
+                    .with_system(open_rebirth_panel)
+                    .with_system(update_rebirth_preview)
+                    .with_system(confirm_rebirth)
+                    .with_system(cancel_rebirth)
+                    .with_system(show_achievement_toasts)
+                    .with_system(expire_achievement_toasts)
+                    .with_system(open_achievements_panel)
+                    .with_system(update_achievement_list)
+                    .with_system(close_achievements_panel),
             );
     }
 }
 
-#[derive(Default, Inspectable)]
-pub struct Score(pub f32);
+/// Inserted when a save game is loaded and the farm earned money while the game was closed
+pub struct OfflineProgress {
+    pub seconds: u64,
+    pub money: Money,
+    pub eggs: u8,
+}
 
+#[derive(Component)]
+struct Sidebar;
 #[derive(Component)]
 struct ScoreText;
 #[derive(Component)]
+struct IncomeText;
+#[derive(Component)]
 struct CurrentEggText;
 #[derive(Component)]
 struct CurrentEggTimerText;
 #[derive(Component)]
 struct MaxEggText;
 #[derive(Component)]
-struct DecreaseEggTimeButton;
+struct AutomationText;
+#[derive(Component)]
+struct UpgradeButton(UpgradeId);
 #[derive(Component)]
-struct IncreaseMaxEggsButton;
+struct UpgradeText(UpgradeId);
+#[derive(Component)]
+struct StarsText;
+#[derive(Component)]
+struct RebirthButton;
+#[derive(Component)]
+struct AchievementsButton;
+
+/// Keep the animals left of the sidebar, however wide the layout made it
+fn fit_playfield_to_sidebar(
+    sidebar: Query<&Node, (With<Sidebar>, Changed<Node>)>,
+    mut playfield: ResMut<Playfield>,
+) {
+    if let Ok(node) = sidebar.get_single() {
+        if node.size.x != playfield.sidebar_width() {
+            playfield.set_sidebar_width(node.size.x);
+        }
+    }
+}
+
+/// Shared by the menus and the sidebar
+fn spawn_ui_camera(mut commands: Commands) {
+    commands.spawn_bundle(UiCameraBundle::default());
+}
 
 fn spawn_ui(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
     texture_assets: Res<TextureAssets>,
+    generations: Res<Generations>,
+    upgrades: Res<Upgrades>,
 ) {
-    commands.spawn_bundle(UiCameraBundle::default());
     commands
         .spawn_bundle(NodeBundle {
             style: Style {
-                size: Size::new(Val::Px(UI_WIDTH), Val::Percent(100.)),
+                size: Size::new(Val::Px(SIDEBAR_WIDTH), Val::Percent(100.)),
                 position_type: PositionType::Absolute,
                 justify_content: JustifyContent::FlexStart,
                 align_items: AlignItems::FlexStart,
@@ -71,6 +133,7 @@ fn spawn_ui(
             color: UiColor(Color::NONE),
             ..Default::default()
         })
+        .insert(Sidebar)
         // Money
         .with_children(|parent| {
             parent
@@ -124,6 +187,32 @@ fn spawn_ui(
                         .insert(ScoreText);
                 });
         })
+        // Income
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(TextBundle {
+                    style: Style {
+                        position: Rect {
+                            left: Val::Px(45.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    text: Text {
+                        sections: vec![TextSection {
+                            value: "+0 G/s".to_string(),
+                            style: TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 20.0,
+                                color: Color::rgb_u8(34, 32, 52),
+                            },
+                        }],
+                        alignment: Default::default(),
+                    },
+                    ..Default::default()
+                })
+                .insert(IncomeText);
+        })
         // Egg timer
         .with_children(|parent| {
             parent
@@ -185,39 +274,6 @@ fn spawn_ui(
                             ..Default::default()
                         })
                         .insert(CurrentEggTimerText);
-                    parent
-                        .spawn_bundle(ButtonBundle {
-                            style: Style {
-                                size: Size::new(Val::Px(32.0), Val::Px(32.0)),
-                                margin: Rect::all(Val::Auto),
-                                justify_content: JustifyContent::Center,
-                                align_items: AlignItems::Center,
-                                position: Rect {
-                                    left: Val::Px(10.),
-                                    ..default()
-                                },
-                                ..Default::default()
-                            },
-                            color: button_colors.normal,
-                            ..Default::default()
-                        })
-                        .insert(DecreaseEggTimeButton)
-                        .with_children(|parent| {
-                            parent.spawn_bundle(TextBundle {
-                                text: Text {
-                                    sections: vec![TextSection {
-                                        value: "-".to_string(),
-                                        style: TextStyle {
-                                            font: font_assets.fira_sans.clone(),
-                                            font_size: 40.0,
-                                            color: Color::rgb(0.9, 0.9, 0.9),
-                                        },
-                                    }],
-                                    alignment: Default::default(),
-                                },
-                                ..Default::default()
-                            });
-                        });
                 });
         })
         // Current max eggs
@@ -301,41 +357,230 @@ fn spawn_ui(
                             ..Default::default()
                         })
                         .insert(MaxEggText);
+                });
+        })
+        // Automation
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::FlexStart,
+                        align_items: AlignItems::Center,
+                        position: Rect {
+                            left: Val::Px(5.),
+                            top: Val::Px(5.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    color: UiColor(Color::NONE),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(70.), Val::Undefined),
+                                ..default()
+                            },
+                            text: Text::with_section(
+                                "Auto: off",
+                                TextStyle {
+                                    font: font_assets.fira_sans.clone(),
+                                    font_size: 20.0,
+                                    color: Color::rgb_u8(34, 32, 52),
+                                },
+                                Default::default(),
+                            ),
+                            ..default()
+                        })
+                        .insert(AutomationText);
+                });
+        })
+        // Upgrades
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        size: Size::new(Val::Percent(100.), Val::Px(UPGRADE_LIST_HEIGHT)),
+                        overflow: Overflow::Hidden,
+                        margin: Rect {
+                            top: Val::Px(5.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    color: UiColor(Color::NONE),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::ColumnReverse,
+                                flex_grow: 1.,
+                                max_size: Size::new(Val::Undefined, Val::Undefined),
+                                ..default()
+                            },
+                            color: UiColor(Color::NONE),
+                            ..default()
+                        })
+                        .insert(ScrollingList::default())
+                        .with_children(|parent| {
+                            for (upgrade, _) in upgrades.iter() {
+                                parent
+                                    .spawn_bundle(ButtonBundle {
+                                        style: Style {
+                                            size: Size::new(
+                                                Val::Percent(100.),
+                                                Val::Px(UPGRADE_ROW_HEIGHT),
+                                            ),
+                                            flex_shrink: 0.,
+                                            align_items: AlignItems::Center,
+                                            padding: Rect {
+                                                left: Val::Px(5.),
+                                                ..default()
+                                            },
+                                            margin: Rect {
+                                                bottom: Val::Px(2.),
+                                                ..default()
+                                            },
+                                            ..default()
+                                        },
+                                        color: button_colors.normal,
+                                        ..default()
+                                    })
+                                    .insert(UpgradeButton(upgrade))
+                                    .with_children(|parent| {
+                                        let style = TextStyle {
+                                            font: font_assets.fira_sans.clone(),
+                                            font_size: 15.0,
+                                            color: Color::rgb(0.9, 0.9, 0.9),
+                                        };
+                                        parent
+                                            .spawn_bundle(TextBundle {
+                                                text: Text {
+                                                    sections: vec![
+                                                        TextSection {
+                                                            value: "".to_string(),
+                                                            style: style.clone(),
+                                                        },
+                                                        TextSection {
+                                                            value: "".to_string(),
+                                                            style,
+                                                        },
+                                                    ],
+                                                    alignment: Default::default(),
+                                                },
+                                                ..default()
+                                            })
+                                            .insert(UpgradeText(upgrade));
+                                    });
+                            }
+                        });
+                });
+        })
+        // Prestige
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        justify_content: JustifyContent::FlexStart,
+                        align_items: AlignItems::Center,
+                        position: Rect {
+                            left: Val::Px(5.),
+                            top: Val::Px(5.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    color: UiColor(Color::NONE),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "0 stars",
+                                TextStyle {
+                                    font: font_assets.fira_sans.clone(),
+                                    font_size: 20.0,
+                                    color: Color::rgb_u8(34, 32, 52),
+                                },
+                                Default::default(),
+                            ),
+                            ..default()
+                        })
+                        .insert(StarsText);
                     parent
                         .spawn_bundle(ButtonBundle {
                             style: Style {
-                                size: Size::new(Val::Px(32.0), Val::Px(32.0)),
-                                margin: Rect::all(Val::Auto),
+                                size: Size::new(Val::Px(80.0), Val::Px(32.0)),
                                 justify_content: JustifyContent::Center,
                                 align_items: AlignItems::Center,
                                 position: Rect {
                                     left: Val::Px(10.),
                                     ..default()
                                 },
-                                ..Default::default()
+                                ..default()
                             },
                             color: button_colors.normal,
-                            ..Default::default()
+                            ..default()
                         })
-                        .insert(IncreaseMaxEggsButton)
+                        .insert(RebirthButton)
                         .with_children(|parent| {
                             parent.spawn_bundle(TextBundle {
-                                text: Text {
-                                    sections: vec![TextSection {
-                                        value: "+".to_string(),
-                                        style: TextStyle {
-                                            font: font_assets.fira_sans.clone(),
-                                            font_size: 40.0,
-                                            color: Color::rgb(0.9, 0.9, 0.9),
-                                        },
-                                    }],
-                                    alignment: Default::default(),
-                                },
-                                ..Default::default()
+                                text: Text::with_section(
+                                    "Rebirth",
+                                    TextStyle {
+                                        font: font_assets.fira_sans.clone(),
+                                        font_size: 20.0,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                    Default::default(),
+                                ),
+                                ..default()
                             });
                         });
                 });
         })
+        // Achievements
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(150.0), Val::Px(28.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        position: Rect {
+                            left: Val::Px(5.),
+                            top: Val::Px(5.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    color: button_colors.normal,
+                    ..default()
+                })
+                .insert(AchievementsButton)
+                .with_children(|parent| {
+                    parent.spawn_bundle(TextBundle {
+                        text: Text::with_section(
+                            "Achievements",
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 20.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                            Default::default(),
+                        ),
+                        ..default()
+                    });
+                });
+        })
         // Explain text
         .with_children(|parent| {
             parent
@@ -387,7 +632,7 @@ fn spawn_ui(
                     ..default()
                 })
                 .with_children(|parent| {
-                    for animal in AnimalGeneration::iter() {
+                    for (animal, _) in generations.iter() {
                         parent
                             .spawn_bundle(NodeBundle {
                                 style: Style {
@@ -414,26 +659,46 @@ struct UiAnimal(AnimalGeneration);
 struct QuestionMark;
 
 fn update_question_marks(
-    mut question_marks: Query<(&mut UiImage, &UiAnimal), (With<QuestionMark>, Without<Animal>)>,
+    mut question_marks: Query<
+        (&mut UiImage, &mut UiColor, &UiAnimal),
+        (With<QuestionMark>, Without<Animal>),
+    >,
     new_animal: Query<&Animal, Added<Animal>>,
-    textures: Res<TextureAssets>,
+    generations: Res<Generations>,
 ) {
-    for (mut image, ui_animal) in question_marks.iter_mut() {
+    for (mut image, mut color, ui_animal) in question_marks.iter_mut() {
         let new_animal = new_animal
             .iter()
             .find(|animal| animal.generation == ui_animal.0);
         if new_animal.is_some() {
-            image.0 = ui_animal.0.get_texture(&textures);
+            image.0 = generations[ui_animal.0].texture.clone();
+            color.0 = generations[ui_animal.0].color;
         }
     }
 }
 
-fn update_score(mut score_text: Query<&mut Text, With<ScoreText>>, score: Res<Score>) {
-    if score.is_changed() {
-        score_text.single_mut().sections[0].value = format!("{:.0}", score.0.floor());
+fn update_score(
+    mut score_text: Query<&mut Text, With<ScoreText>>,
+    score: Res<Score>,
+    settings: Res<Settings>,
+) {
+    if score.is_changed() || settings.is_changed() {
+        score_text.single_mut().sections[0].value = settings.number_format.format(score.0.floor());
     }
 }
 
+fn update_income(
+    mut income_text: Query<&mut Text, With<IncomeText>>,
+    animals: Query<&Animal>,
+    generations: Res<Generations>,
+    prestige: Res<Prestige>,
+    settings: Res<Settings>,
+) {
+    let income = income(animals.iter(), &generations, &prestige);
+    income_text.single_mut().sections[0].value =
+        format!("+{} G/s", settings.number_format.format(income));
+}
+
 fn update_current_eggs(
     mut egg_text: Query<&mut Text, With<CurrentEggText>>,
     current_eggs: Res<CurrentEggs>,
@@ -452,99 +717,831 @@ fn update_current_max_eggs(
     }
 }
 
-pub struct MaxEggPrice(pub f32);
+fn update_egg_time(
+    current_egg_time: Res<CurrentEggTime>,
+    mut egg_time_text: Query<&mut Text, With<CurrentEggTimerText>>,
+) {
+    if current_egg_time.is_changed() {
+        egg_time_text
+            .single_mut()
+            .sections
+            .get_mut(0)
+            .unwrap()
+            .value = format!("{:.0}", current_egg_time.0.floor());
+    }
+}
+
+#[derive(Component)]
+struct OfflineProgressPanel;
+
+#[derive(Component)]
+struct CloseOfflineProgressButton;
+
+fn show_offline_progress(
+    mut commands: Commands,
+    offline_progress: Option<Res<OfflineProgress>>,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    settings: Res<Settings>,
+) {
+    let offline_progress = match offline_progress {
+        Some(offline_progress) if offline_progress.is_added() => offline_progress,
+        _ => return,
+    };
+    let text_style = TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size: 25.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    let mut lines = vec![
+        format!(
+            "While you were away ({})",
+            format_duration(offline_progress.seconds)
+        ),
+        format!(
+            "+{} G",
+            settings
+                .number_format
+                .format(offline_progress.money.floor())
+        ),
+    ];
+    if offline_progress.eggs > 0 {
+        lines.push(format!("+{} eggs", offline_progress.eggs));
+    }
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.),
+                    right: Val::Px(SIDEBAR_WIDTH),
+                    top: Val::Px(0.),
+                    bottom: Val::Px(0.),
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(OfflineProgressPanel)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        padding: Rect::all(Val::Px(15.)),
+                        ..default()
+                    },
+                    color: UiColor(Color::rgba(0.1, 0.1, 0.1, 0.9)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for line in lines {
+                        parent.spawn_bundle(TextBundle {
+                            text: Text::with_section(line, text_style.clone(), Default::default()),
+                            ..default()
+                        });
+                    }
+                    parent
+                        .spawn_bundle(ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(120.0), Val::Px(40.0)),
+                                margin: Rect {
+                                    top: Val::Px(10.),
+                                    ..default()
+                                },
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            color: button_colors.normal,
+                            ..default()
+                        })
+                        .insert(CloseOfflineProgressButton)
+                        .with_children(|parent| {
+                            parent.spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    "Collect",
+                                    text_style.clone(),
+                                    Default::default(),
+                                ),
+                                ..default()
+                            });
+                        });
+                });
+        });
+}
 
-fn buy_max_egg(
+fn close_offline_progress(
+    mut commands: Commands,
     button_colors: Res<ButtonColors>,
-    mut current_max_eggs: ResMut<CurrentMaxEggs>,
-    mut max_egg_price: ResMut<MaxEggPrice>,
-    mut score: ResMut<Score>,
     mut interaction_query: Query<
         (&Interaction, &mut UiColor),
-        (
-            Changed<Interaction>,
-            With<IncreaseMaxEggsButton>,
-            Without<ExplainText>,
-        ),
+        (Changed<Interaction>, With<CloseOfflineProgressButton>),
     >,
-    mut explain_text: Query<&mut Text, With<ExplainText>>,
+    panel: Query<Entity, With<OfflineProgressPanel>>,
 ) {
     for (interaction, mut color) in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
-                if score.0 > max_egg_price.0 {
-                    score.0 -= max_egg_price.0;
-                    current_max_eggs.0 += 1;
-                    max_egg_price.0 *= 10.;
+                for entity in panel.iter() {
+                    commands.entity(entity).despawn_recursive();
                 }
+                commands.remove_resource::<OfflineProgress>();
             }
             Interaction::Hovered => {
                 *color = button_colors.hovered;
-                let mut text = explain_text.single_mut();
-                text.sections.get_mut(0).unwrap().value =
-                    format!("+1 max eggs for {} G", max_egg_price.0);
             }
             Interaction::None => {
                 *color = button_colors.normal;
-                let mut text = explain_text.single_mut();
-                text.sections.get_mut(0).unwrap().value = "".to_owned();
             }
         }
     }
 }
 
-pub struct EggTimePrice(pub f32);
+fn update_automation(
+    mut automation_text: Query<&mut Text, With<AutomationText>>,
+    automation: Res<Automation>,
+) {
+    if automation.is_changed() {
+        automation_text.single_mut().sections[0].value =
+            if automation.auto_hatch || automation.auto_merge {
+                format!("Auto: {:.1}s", automation.interval)
+            } else {
+                "Auto: off".to_string()
+            };
+    }
+}
+
+/// Name, level and price of every upgrade, dimmed if it cannot be bought
+fn update_upgrade_list(
+    mut texts: Query<(&mut Text, &UpgradeText)>,
+    upgrades: Res<Upgrades>,
+    levels: Res<UpgradeLevels>,
+    score: Res<Score>,
+    settings: Res<Settings>,
+) {
+    if !score.is_changed() && !levels.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for (mut text, UpgradeText(upgrade)) in texts.iter_mut() {
+        let definition = &upgrades[*upgrade];
+        let level = levels.level(*upgrade);
+        text.sections[0].value = match definition.max_level {
+            Some(1) => definition.name.clone(),
+            _ => format!("{} {}", definition.name, level),
+        };
+        let (price, color) = match upgrades.check(*upgrade, &levels, score.0) {
+            Ok(price) => (
+                settings.number_format.format(price),
+                Color::rgb(0.9, 0.9, 0.9),
+            ),
+            Err(Unavailable::TooExpensive(price)) => (
+                settings.number_format.format(price),
+                Color::rgb(0.6, 0.6, 0.6),
+            ),
+            Err(Unavailable::MaxLevel) => ("max".to_string(), Color::rgb(0.6, 0.6, 0.6)),
+            Err(Unavailable::Requires(..)) => ("locked".to_string(), Color::rgb(0.4, 0.4, 0.4)),
+        };
+        text.sections[1].value = format!("  {}", price);
+        for section in text.sections.iter_mut() {
+            section.style.color = color;
+        }
+    }
+}
 
-fn buy_faster_eggs(
+fn buy_upgrades(
     button_colors: Res<ButtonColors>,
-    mut current_egg_time: ResMut<CurrentEggTime>,
-    mut egg_time_price: ResMut<EggTimePrice>,
-    mut score: ResMut<Score>,
+    upgrades: Res<Upgrades>,
+    levels: Res<UpgradeLevels>,
+    score: Res<Score>,
+    mut buy_upgrade: EventWriter<BuyUpgrade>,
     mut interaction_query: Query<
-        (&Interaction, &mut UiColor),
-        (
-            Changed<Interaction>,
-            With<DecreaseEggTimeButton>,
-            Without<ExplainText>,
-        ),
+        (&Interaction, &mut UiColor, &UpgradeButton),
+        (Changed<Interaction>, Without<ExplainText>),
     >,
     mut explain_text: Query<&mut Text, With<ExplainText>>,
+    settings: Res<Settings>,
 ) {
-    for (interaction, mut color) in interaction_query.iter_mut() {
+    for (interaction, mut color, UpgradeButton(upgrade)) in interaction_query.iter_mut() {
         match *interaction {
             Interaction::Clicked => {
-                if score.0 > egg_time_price.0 && current_egg_time.0 > 1.5 {
-                    score.0 -= egg_time_price.0;
-                    current_egg_time.0 -= 1.;
-                    egg_time_price.0 *= 5.;
+                buy_upgrade.send(BuyUpgrade(*upgrade));
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+                let definition = &upgrades[*upgrade];
+                let availability = match upgrades.check(*upgrade, &levels, score.0) {
+                    Ok(price) | Err(Unavailable::TooExpensive(price)) => {
+                        format!("for {} G", settings.number_format.format(price))
+                    }
+                    Err(Unavailable::MaxLevel) => "(max level)".to_string(),
+                    Err(Unavailable::Requires(name, 1)) => format!("(requires {})", name),
+                    Err(Unavailable::Requires(name, level)) => {
+                        format!("(requires {} {})", name, level)
+                    }
+                };
+                explain_text.single_mut().sections[0].value = format!(
+                    "{} {}",
+                    definition.stat.describe(definition.effect),
+                    availability
+                );
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+                explain_text.single_mut().sections[0].value = "".to_owned();
+            }
+        }
+    }
+}
+
+#[derive(Component, Default)]
+struct ScrollingList {
+    position: f32,
+}
+
+/// Scroll the upgrades with the mouse wheel while the cursor is over the sidebar
+fn scroll_upgrade_list(
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    windows: Res<Windows>,
+    playfield: Res<Playfield>,
+    mut lists: Query<(&mut ScrollingList, &mut Style, &Children, &Node)>,
+    items: Query<&Node>,
+) {
+    let over_sidebar = windows
+        .get_primary()
+        .and_then(|window| {
+            window
+                .cursor_position()
+                .map(|cursor| cursor.x > window.width() - playfield.sidebar_width())
+        })
+        .unwrap_or(false);
+    for event in mouse_wheel_events.iter() {
+        if !over_sidebar {
+            continue;
+        }
+        let scrolled = match event.unit {
+            MouseScrollUnit::Line => event.y * UPGRADE_ROW_HEIGHT,
+            MouseScrollUnit::Pixel => event.y,
+        };
+        for (mut list, mut style, children, node) in lists.iter_mut() {
+            let items_height: f32 = children
+                .iter()
+                .filter_map(|child| items.get(*child).ok())
+                .map(|item| item.size.y)
+                .sum();
+            let max_scroll = (items_height - node.size.y).max(0.);
+            list.position = (list.position + scrolled).clamp(-max_scroll, 0.);
+            style.position.top = Val::Px(list.position);
+        }
+    }
+}
+
+fn update_stars(mut stars_text: Query<&mut Text, With<StarsText>>, prestige: Res<Prestige>) {
+    if prestige.is_changed() {
+        stars_text.single_mut().sections[0].value = match prestige.stars {
+            1 => "1 star".to_string(),
+            stars => format!("{} stars", stars),
+        };
+    }
+}
+
+#[derive(Component)]
+struct RebirthPanel;
+
+#[derive(Component)]
+struct RebirthPreviewText;
+
+#[derive(Component)]
+struct ConfirmRebirthButton;
+
+#[derive(Component)]
+struct CancelRebirthButton;
+
+fn open_rebirth_panel(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<RebirthButton>),
+    >,
+    panel: Query<Entity, With<RebirthPanel>>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                if panel.is_empty() {
+                    spawn_rebirth_panel(&mut commands, &font_assets, &button_colors);
                 }
             }
             Interaction::Hovered => {
                 *color = button_colors.hovered;
-                let mut text = explain_text.single_mut();
-                text.sections.get_mut(0).unwrap().value =
-                    format!("-1s for egg: {} G", egg_time_price.0);
             }
             Interaction::None => {
                 *color = button_colors.normal;
-                let mut text = explain_text.single_mut();
-                text.sections.get_mut(0).unwrap().value = "".to_owned();
             }
         }
     }
 }
 
-fn update_egg_time(
-    current_egg_time: Res<CurrentEggTime>,
-    mut egg_time_text: Query<&mut Text, With<CurrentEggTimerText>>,
+fn spawn_rebirth_panel(
+    commands: &mut Commands,
+    font_assets: &FontAssets,
+    button_colors: &ButtonColors,
 ) {
-    if current_egg_time.is_changed() {
-        egg_time_text
-            .single_mut()
-            .sections
-            .get_mut(0)
-            .unwrap()
-            .value = format!("{:.0}", current_egg_time.0.floor());
+    let text_style = TextStyle {
+        font: font_assets.fira_sans.clone(),
+        font_size: 25.0,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.),
+                    right: Val::Px(SIDEBAR_WIDTH),
+                    top: Val::Px(0.),
+                    bottom: Val::Px(0.),
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(RebirthPanel)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        padding: Rect::all(Val::Px(15.)),
+                        ..default()
+                    },
+                    color: UiColor(Color::rgba(0.1, 0.1, 0.1, 0.9)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                "",
+                                text_style.clone(),
+                                TextAlignment {
+                                    horizontal: HorizontalAlign::Center,
+                                    ..default()
+                                },
+                            ),
+                            ..default()
+                        })
+                        .insert(RebirthPreviewText);
+                    parent
+                        .spawn_bundle(NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Row,
+                                margin: Rect {
+                                    top: Val::Px(10.),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            color: UiColor(Color::NONE),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent
+                                .spawn_bundle(ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(120.0), Val::Px(40.0)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    color: button_colors.normal,
+                                    ..default()
+                                })
+                                .insert(ConfirmRebirthButton)
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(TextBundle {
+                                        text: Text::with_section(
+                                            "Rebirth",
+                                            text_style.clone(),
+                                            Default::default(),
+                                        ),
+                                        ..default()
+                                    });
+                                });
+                            parent
+                                .spawn_bundle(ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(120.0), Val::Px(40.0)),
+                                        margin: Rect {
+                                            left: Val::Px(10.),
+                                            ..default()
+                                        },
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        ..default()
+                                    },
+                                    color: button_colors.normal,
+                                    ..default()
+                                })
+                                .insert(CancelRebirthButton)
+                                .with_children(|parent| {
+                                    parent.spawn_bundle(TextBundle {
+                                        text: Text::with_section(
+                                            "Cancel",
+                                            text_style.clone(),
+                                            Default::default(),
+                                        ),
+                                        ..default()
+                                    });
+                                });
+                        });
+                });
+        });
+}
+
+/// Keep the projected reward up to date while the panel is open, the farm keeps earning
+fn update_rebirth_preview(
+    mut preview_text: Query<&mut Text, With<RebirthPreviewText>>,
+    prestige: Res<Prestige>,
+    settings: Res<Settings>,
+) {
+    let mut text = match preview_text.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let reward = prestige.projected_reward();
+    let lines = if reward == 0 {
+        vec![
+            "Not enough gold earned for a star yet".to_string(),
+            format!(
+                "Earn {} G in total for the next one",
+                settings
+                    .number_format
+                    .format(prestige.earnings_for_next_star())
+            ),
+        ]
+    } else {
+        let stars = prestige.stars + reward;
+        vec![
+            "Reset your farm, gold and upgrades".to_string(),
+            format!("+{} stars", reward),
+            format!(
+                "Income x{:.2} -> x{:.2}",
+                prestige.income_multiplier(),
+                Prestige::income_multiplier_for(stars)
+            ),
+            format!(
+                "Egg speed x{:.2} -> x{:.2}",
+                prestige.egg_speed_multiplier(),
+                Prestige::egg_speed_multiplier_for(stars)
+            ),
+        ]
+    };
+    text.sections[0].value = lines.join("\n");
+}
+
+fn confirm_rebirth(
+    mut commands: Commands,
+    button_colors: Res<ButtonColors>,
+    mut rebirth: EventWriter<Rebirth>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<ConfirmRebirthButton>),
+    >,
+    panel: Query<Entity, With<RebirthPanel>>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                rebirth.send(Rebirth);
+                for entity in panel.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+            }
+        }
+    }
+}
+
+fn cancel_rebirth(
+    mut commands: Commands,
+    button_colors: Res<ButtonColors>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<CancelRebirthButton>),
+    >,
+    panel: Query<Entity, With<RebirthPanel>>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                for entity in panel.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+            }
+        }
+    }
+}
+
+#[derive(Component)]
+struct AchievementToast(Timer);
+
+fn show_achievement_toasts(
+    mut commands: Commands,
+    mut unlock_events: EventReader<AchievementUnlocked>,
+    achievements: Res<Achievements>,
+    font_assets: Res<FontAssets>,
+    toasts: Query<(), With<AchievementToast>>,
+) {
+    let mut stacked = toasts.iter().count();
+    for AchievementUnlocked(achievement) in unlock_events.iter() {
+        let achievement = &achievements[*achievement];
+        commands
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(10.),
+                        top: Val::Px(10. + 55. * stacked as f32),
+                        ..default()
+                    },
+                    flex_direction: FlexDirection::ColumnReverse,
+                    padding: Rect::all(Val::Px(8.)),
+                    ..default()
+                },
+                color: UiColor(Color::rgba(0.1, 0.1, 0.1, 0.9)),
+                ..default()
+            })
+            .insert(AchievementToast(Timer::from_seconds(TOAST_SECONDS, false)))
+            .with_children(|parent| {
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        format!("Achievement unlocked: {}", achievement.name),
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 20.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                        Default::default(),
+                    ),
+                    ..default()
+                });
+                parent.spawn_bundle(TextBundle {
+                    text: Text::with_section(
+                        achievement.description.clone(),
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 15.0,
+                            color: Color::rgb(0.7, 0.7, 0.7),
+                        },
+                        Default::default(),
+                    ),
+                    ..default()
+                });
+            });
+        stacked += 1;
+    }
+}
+
+fn expire_achievement_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut AchievementToast)>,
+) {
+    for (entity, mut toast) in toasts.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[derive(Component)]
+struct AchievementsPanel;
+
+#[derive(Component)]
+struct AchievementListText;
+
+#[derive(Component)]
+struct CloseAchievementsButton;
+
+fn open_achievements_panel(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<AchievementsButton>),
+    >,
+    panel: Query<Entity, With<AchievementsPanel>>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                if panel.is_empty() {
+                    spawn_achievements_panel(&mut commands, &font_assets, &button_colors);
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+            }
+        }
+    }
+}
+
+fn spawn_achievements_panel(
+    commands: &mut Commands,
+    font_assets: &FontAssets,
+    button_colors: &ButtonColors,
+) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(0.),
+                    right: Val::Px(SIDEBAR_WIDTH),
+                    top: Val::Px(0.),
+                    bottom: Val::Px(0.),
+                },
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            color: UiColor(Color::NONE),
+            ..default()
+        })
+        .insert(AchievementsPanel)
+        .with_children(|parent| {
+            parent
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::ColumnReverse,
+                        align_items: AlignItems::Center,
+                        padding: Rect::all(Val::Px(15.)),
+                        ..default()
+                    },
+                    color: UiColor(Color::rgba(0.1, 0.1, 0.1, 0.9)),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn_bundle(TextBundle {
+                            text: Text {
+                                sections: vec![],
+                                alignment: Default::default(),
+                            },
+                            ..default()
+                        })
+                        .insert(AchievementListText);
+                    parent
+                        .spawn_bundle(ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(120.0), Val::Px(40.0)),
+                                margin: Rect {
+                                    top: Val::Px(10.),
+                                    ..default()
+                                },
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            color: button_colors.normal,
+                            ..default()
+                        })
+                        .insert(CloseAchievementsButton)
+                        .with_children(|parent| {
+                            parent.spawn_bundle(TextBundle {
+                                text: Text::with_section(
+                                    "Close",
+                                    TextStyle {
+                                        font: font_assets.fira_sans.clone(),
+                                        font_size: 25.0,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                    Default::default(),
+                                ),
+                                ..default()
+                            });
+                        });
+                });
+        });
+}
+
+/// One line per achievement; locked ones are dimmed and show how far the player got
+fn update_achievement_list(
+    mut list_text: Query<&mut Text, With<AchievementListText>>,
+    achievements: Res<Achievements>,
+    unlocked: Res<UnlockedAchievements>,
+    statistics: Res<Statistics>,
+    prestige: Res<Prestige>,
+    generations: Res<Generations>,
+    settings: Res<Settings>,
+    font_assets: Res<FontAssets>,
+) {
+    let mut text = match list_text.get_single_mut() {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    text.sections = achievements
+        .iter()
+        .map(|(id, achievement)| {
+            let (value, color) = if unlocked.0.contains(&id) {
+                (
+                    format!("{} - {}\n", achievement.name, achievement.description),
+                    Color::rgb(0.9, 0.9, 0.9),
+                )
+            } else {
+                let progress = match &achievement.condition {
+                    Condition::EverBred(_) => String::new(),
+                    condition => {
+                        let (current, target) =
+                            condition.progress(&statistics, &prestige, &generations);
+                        format!(
+                            " ({}/{})",
+                            settings.number_format.format(Money::from(current).floor()),
+                            settings.number_format.format(Money::from(target))
+                        )
+                    }
+                };
+                (
+                    format!(
+                        "{} - {}{}\n",
+                        achievement.name, achievement.description, progress
+                    ),
+                    Color::rgb(0.5, 0.5, 0.5),
+                )
+            };
+            TextSection {
+                value,
+                style: TextStyle {
+                    font: font_assets.fira_sans.clone(),
+                    font_size: 18.0,
+                    color,
+                },
+            }
+        })
+        .collect();
+}
+
+fn close_achievements_panel(
+    mut commands: Commands,
+    button_colors: Res<ButtonColors>,
+    mut interaction_query: Query<
+        (&Interaction, &mut UiColor),
+        (Changed<Interaction>, With<CloseAchievementsButton>),
+    >,
+    panel: Query<Entity, With<AchievementsPanel>>,
+) {
+    for (interaction, mut color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Clicked => {
+                for entity in panel.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            Interaction::Hovered => {
+                *color = button_colors.hovered;
+            }
+            Interaction::None => {
+                *color = button_colors.normal;
+            }
+        }
+    }
+}
+
+fn format_duration(seconds: u64) -> String {
+    let hours = seconds / 3600;
+    let minutes = seconds % 3600 / 60;
+    if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds % 60)
+    } else {
+        format!("{}s", seconds)
     }
 }
 
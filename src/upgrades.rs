@@ -0,0 +1,348 @@
+use crate::automation::{Automation, DEFAULT_AUTOMATION_INTERVAL, MIN_AUTOMATION_INTERVAL};
+use crate::economy::Score;
+use crate::events::UpgradePurchased;
+use crate::farm::{CurrentEggTime, CurrentMaxEggs};
+use crate::money::Money;
+use crate::GameState;
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use std::ops::Index;
+
+/// The egg timer cannot be upgraded below this many seconds
+const MIN_EGG_TIME: f64 = 1.;
+
+pub struct UpgradesPlugin;
+
+impl Plugin for UpgradesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<UpgradeLevels>()
+            .add_event::<BuyUpgrade>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(buy_upgrades)
+                    .with_system(apply_upgrades.after(buy_upgrades)),
+            );
+    }
+}
+
+/// Game values that upgrades can change
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stat {
+    MaxEggs,
+    /// Seconds between two eggs
+    EggTime,
+    /// Hatch eggs automatically, if above zero
+    AutoHatch,
+    /// Merge pairs automatically, if above zero
+    AutoMerge,
+    /// Seconds between two automation ticks
+    AutomationInterval,
+}
+
+impl Stat {
+    /// Value without any upgrades
+    fn base(&self) -> f64 {
+        match self {
+            Stat::MaxEggs => CurrentMaxEggs::default().0 as f64,
+            Stat::EggTime => CurrentEggTime::default().0 as f64,
+            Stat::AutoHatch | Stat::AutoMerge => 0.,
+            Stat::AutomationInterval => DEFAULT_AUTOMATION_INTERVAL as f64,
+        }
+    }
+
+    /// Keep upgraded values in a range the game can handle
+    fn clamp(&self, value: f64) -> f64 {
+        match self {
+            Stat::MaxEggs => value.clamp(0., u8::MAX as f64),
+            Stat::EggTime => value.max(MIN_EGG_TIME),
+            Stat::AutoHatch | Stat::AutoMerge => value,
+            Stat::AutomationInterval => value.max(MIN_AUTOMATION_INTERVAL as f64),
+        }
+    }
+
+    /// Player facing description of changing this stat by `effect`
+    pub fn describe(&self, effect: f64) -> String {
+        match self {
+            Stat::MaxEggs => format!("{:+} max eggs", effect),
+            Stat::EggTime => format!("{:+}s per egg", effect),
+            Stat::AutoHatch => "hatch eggs automatically".to_string(),
+            Stat::AutoMerge => "merge pairs automatically".to_string(),
+            Stat::AutomationInterval => format!("{:+}s per automation tick", effect),
+        }
+    }
+}
+
+/// Price of the next level is `base * growth^level`
+#[derive(Deserialize, Clone, Debug)]
+pub struct CostCurve {
+    pub base: f64,
+    pub growth: f64,
+}
+
+impl CostCurve {
+    pub fn cost(&self, level: u32) -> Money {
+        Money::from(self.base) * Money::from(self.growth).powi(level)
+    }
+}
+
+/// One entry of the shop as defined in `shop.upgrades.ron`
+#[derive(Deserialize, Clone, Debug)]
+pub struct Upgrade {
+    /// Stable identifier used in save games and prerequisites
+    pub id: String,
+    pub name: String,
+    pub stat: Stat,
+    /// Change of the stat per level
+    pub effect: f64,
+    pub cost: CostCurve,
+    #[serde(default)]
+    pub max_level: Option<u32>,
+    /// Other upgrades and the level they need before this one can be bought
+    #[serde(default)]
+    pub requires: Vec<(String, u32)>,
+}
+
+/// Index into the [`Upgrades`] loaded from `shop.upgrades.ron`
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct UpgradeId(pub usize);
+
+/// All upgrades in the order they are listed in the shop
+#[derive(Clone, TypeUuid, Deserialize)]
+#[uuid = "6c1f0e8e-2b6d-4c39-9a35-1d3f2b7e54c9"]
+pub struct Upgrades {
+    upgrades: Vec<Upgrade>,
+}
+
+impl Upgrades {
+    pub fn iter(&self) -> impl Iterator<Item = (UpgradeId, &Upgrade)> {
+        self.upgrades
+            .iter()
+            .enumerate()
+            .map(|(index, upgrade)| (UpgradeId(index), upgrade))
+    }
+
+    pub fn by_id(&self, id: &str) -> Option<UpgradeId> {
+        self.upgrades
+            .iter()
+            .position(|upgrade| upgrade.id == id)
+            .map(UpgradeId)
+    }
+}
+
+impl Index<UpgradeId> for Upgrades {
+    type Output = Upgrade;
+
+    fn index(&self, upgrade: UpgradeId) -> &Self::Output {
+        &self.upgrades[upgrade.0]
+    }
+}
+
+/// Levels bought in the current run; everything not in here is at level zero
+#[derive(Default)]
+pub struct UpgradeLevels(pub HashMap<UpgradeId, u32>);
+
+impl UpgradeLevels {
+    pub fn level(&self, upgrade: UpgradeId) -> u32 {
+        self.0.get(&upgrade).copied().unwrap_or_default()
+    }
+}
+
+/// Why an upgrade cannot be bought right now
+pub enum Unavailable<'a> {
+    MaxLevel,
+    /// Name and level of the missing prerequisite
+    Requires(&'a str, u32),
+    TooExpensive(Money),
+}
+
+impl Upgrades {
+    /// Price of the next level, or the reason the upgrade cannot be bought
+    pub fn check(
+        &self,
+        upgrade: UpgradeId,
+        levels: &UpgradeLevels,
+        score: Money,
+    ) -> Result<Money, Unavailable> {
+        let definition = &self[upgrade];
+        let level = levels.level(upgrade);
+        if definition.max_level.map_or(false, |max| level >= max) {
+            return Err(Unavailable::MaxLevel);
+        }
+        for (required, required_level) in definition.requires.iter() {
+            let met = self
+                .by_id(required)
+                .map_or(false, |required| levels.level(required) >= *required_level);
+            if !met {
+                let name = self
+                    .by_id(required)
+                    .map_or(required.as_str(), |required| self[required].name.as_str());
+                return Err(Unavailable::Requires(name, *required_level));
+            }
+        }
+        let price = definition.cost.cost(level);
+        if score < price {
+            return Err(Unavailable::TooExpensive(price));
+        }
+
+        Ok(price)
+    }
+
+    /// Value of the stat with all bought levels applied
+    pub fn stat(&self, stat: Stat, levels: &UpgradeLevels) -> f64 {
+        let upgraded = self
+            .iter()
+            .filter(|(_, upgrade)| upgrade.stat == stat)
+            .fold(stat.base(), |value, (id, upgrade)| {
+                value + upgrade.effect * levels.level(id) as f64
+            });
+
+        stat.clamp(upgraded)
+    }
+}
+
+/// Sent by the shop (or any other player stand-in) to buy the next level of an upgrade
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuyUpgrade(pub UpgradeId);
+
+fn buy_upgrades(
+    mut events: EventReader<BuyUpgrade>,
+    mut score: ResMut<Score>,
+    mut levels: ResMut<UpgradeLevels>,
+    upgrades: Res<Upgrades>,
+    mut purchased: EventWriter<UpgradePurchased>,
+) {
+    for BuyUpgrade(upgrade) in events.iter() {
+        if let Ok(price) = upgrades.check(*upgrade, &levels, score.0) {
+            score.0 -= price;
+            let level = levels.0.entry(*upgrade).or_default();
+            *level += 1;
+            purchased.send(UpgradePurchased {
+                upgrade: *upgrade,
+                level: *level,
+                price,
+            });
+        }
+    }
+}
+
+/// Derive the upgradable game values from the bought levels
+fn apply_upgrades(
+    levels: Res<UpgradeLevels>,
+    upgrades: Res<Upgrades>,
+    mut current_max_eggs: ResMut<CurrentMaxEggs>,
+    mut current_egg_time: ResMut<CurrentEggTime>,
+    mut automation: ResMut<Automation>,
+) {
+    if !levels.is_changed() && !upgrades.is_changed() {
+        return;
+    }
+    let stat = |stat: Stat| upgrades.stat(stat, &levels);
+
+    current_max_eggs.0 = stat(Stat::MaxEggs) as u8;
+    current_egg_time.0 = stat(Stat::EggTime) as f32;
+    automation.auto_hatch = stat(Stat::AutoHatch) > 0.;
+    automation.auto_merge = stat(Stat::AutoMerge) > 0.;
+    automation.interval = stat(Stat::AutomationInterval) as f32;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgrades() -> Upgrades {
+        ron::from_str(
+            r#"(upgrades: [
+                (id: "max_eggs", name: "Max eggs", stat: MaxEggs, effect: 1., cost: (base: 100., growth: 10.)),
+                (id: "more_eggs", name: "More eggs", stat: MaxEggs, effect: 2., cost: (base: 50., growth: 2.)),
+                (id: "faster_eggs", name: "Faster eggs", stat: EggTime, effect: -1., cost: (base: 20., growth: 5.), max_level: Some(3)),
+                (id: "auto_hatch", name: "Auto hatch", stat: AutoHatch, effect: 1., cost: (base: 1000., growth: 1.), max_level: Some(1)),
+                (id: "auto_merge", name: "Auto merge", stat: AutoMerge, effect: 1., cost: (base: 10., growth: 1.), requires: [("auto_hatch", 1)]),
+            ])"#,
+        )
+        .unwrap()
+    }
+
+    fn levels(upgrades: &Upgrades, levels: &[(&str, u32)]) -> UpgradeLevels {
+        UpgradeLevels(
+            levels
+                .iter()
+                .map(|(id, level)| (upgrades.by_id(id).unwrap(), *level))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn cost_grows_geometrically() {
+        let curve = CostCurve {
+            base: 100.,
+            growth: 10.,
+        };
+
+        assert_eq!(curve.cost(0), Money::from(100.));
+        assert_eq!(curve.cost(1), Money::from(1000.));
+        assert_eq!(curve.cost(3), Money::new(1., 5));
+        // far beyond the range of f64
+        assert_eq!(curve.cost(400), Money::new(1., 402));
+    }
+
+    #[test]
+    fn stats_start_at_their_base_value() {
+        let upgrades = upgrades();
+        let none = UpgradeLevels::default();
+
+        assert_eq!(upgrades.stat(Stat::MaxEggs, &none), 1.);
+        assert_eq!(upgrades.stat(Stat::EggTime, &none), 10.);
+        assert_eq!(upgrades.stat(Stat::AutoHatch, &none), 0.);
+        assert_eq!(
+            upgrades.stat(Stat::AutomationInterval, &none),
+            DEFAULT_AUTOMATION_INTERVAL as f64
+        );
+    }
+
+    #[test]
+    fn upgrades_of_the_same_stat_add_up() {
+        let upgrades = upgrades();
+        let levels = levels(&upgrades, &[("max_eggs", 2), ("more_eggs", 3)]);
+
+        assert_eq!(upgrades.stat(Stat::MaxEggs, &levels), 1. + 2. + 6.);
+    }
+
+    #[test]
+    fn stats_are_clamped() {
+        let upgrades = upgrades();
+        let levels = levels(&upgrades, &[("faster_eggs", 20), ("max_eggs", 1000)]);
+
+        assert_eq!(upgrades.stat(Stat::EggTime, &levels), MIN_EGG_TIME);
+        assert_eq!(upgrades.stat(Stat::MaxEggs, &levels), u8::MAX as f64);
+    }
+
+    #[test]
+    fn checks_price_level_and_prerequisites() {
+        let upgrades = upgrades();
+        let faster_eggs = upgrades.by_id("faster_eggs").unwrap();
+        let auto_merge = upgrades.by_id("auto_merge").unwrap();
+        let rich = Money::new(1., 10);
+
+        assert!(matches!(
+            upgrades.check(faster_eggs, &levels(&upgrades, &[("faster_eggs", 1)]), rich),
+            Ok(price) if price == Money::from(100.)
+        ));
+        assert!(matches!(
+            upgrades.check(faster_eggs, &levels(&upgrades, &[("faster_eggs", 3)]), rich),
+            Err(Unavailable::MaxLevel)
+        ));
+        assert!(matches!(
+            upgrades.check(faster_eggs, &UpgradeLevels::default(), Money::from(19.)),
+            Err(Unavailable::TooExpensive(price)) if price == Money::from(20.)
+        ));
+        assert!(matches!(
+            upgrades.check(auto_merge, &UpgradeLevels::default(), rich),
+            Err(Unavailable::Requires("Auto hatch", 1))
+        ));
+        assert!(upgrades
+            .check(auto_merge, &levels(&upgrades, &[("auto_hatch", 1)]), rich)
+            .is_ok());
+    }
+}